@@ -0,0 +1,296 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anchor_client::anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_client::solana_sdk::hash::Hash;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_client::solana_sdk::{system_program, sysvar};
+use anchor_client::Program;
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anyhow::{anyhow, bail, Result};
+
+use senda_smartc::state::{AuthorizedBy, Escrow, Stable};
+use senda_smartc::{accounts, instruction};
+
+pub type SendaProgram = Program<Arc<Keypair>>;
+
+fn factory_address(program_id: &Pubkey, admin: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"factory", admin.as_ref()], program_id).0
+}
+
+fn escrow_address(program_id: &Pubkey, sender: &Pubkey, receiver: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"escrow", sender.as_ref(), receiver.as_ref()], program_id).0
+}
+
+fn vault_address(program_id: &Pubkey, prefix: &[u8], escrow: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[prefix, escrow.as_ref(), mint.as_ref()], program_id).0
+}
+
+fn deposit_record_address(
+    program_id: &Pubkey,
+    escrow: &Pubkey,
+    depositor: &Pubkey,
+    blockhash: &[u8; 32],
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"deposit", escrow.as_ref(), depositor.as_ref(), blockhash.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+fn parse_blockhash(blockhash: &str) -> Result<[u8; 32]> {
+    let hash = Hash::from_str(blockhash).map_err(|e| anyhow!("invalid blockhash {blockhash}: {e}"))?;
+    Ok(hash.to_bytes())
+}
+
+fn counterparty(escrow: &Escrow, depositor: Pubkey) -> Result<Pubkey> {
+    if depositor == escrow.sender {
+        Ok(escrow.receiver)
+    } else if depositor == escrow.receiver {
+        Ok(escrow.sender)
+    } else {
+        bail!("{depositor} is not a party of this escrow")
+    }
+}
+
+// Release and cancel read party signatures from plain `AccountInfo`s, so their
+// metas have to be flagged as signers by hand.
+fn instruction_with_signers(
+    program: &SendaProgram,
+    accounts: impl ToAccountMetas,
+    args: impl InstructionData,
+    signers: &[Keypair],
+) -> Instruction {
+    let mut metas = accounts.to_account_metas(None);
+    for meta in metas.iter_mut() {
+        if signers.iter().any(|signer| signer.pubkey() == meta.pubkey) {
+            meta.is_signer = true;
+        }
+    }
+
+    Instruction {
+        program_id: program.id(),
+        accounts: metas,
+        data: args.data(),
+    }
+}
+
+pub fn init_factory(program: &SendaProgram, authority: &Keypair) -> Result<()> {
+    let factory = factory_address(&program.id(), &authority.pubkey());
+    let (mint_authority, _) =
+        Pubkey::find_program_address(&[b"mint_auth", factory.as_ref()], &program.id());
+
+    let signature = program
+        .request()
+        .accounts(accounts::InitializeFactory {
+            factory,
+            mint_authority,
+            authority: authority.pubkey(),
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        })
+        .args(instruction::InitFactory {})
+        .send()?;
+
+    println!("factory: {factory}");
+    println!("signature: {signature}");
+    Ok(())
+}
+
+pub fn create_escrow(
+    program: &SendaProgram,
+    authority: &Keypair,
+    sender: &Keypair,
+    receiver: Pubkey,
+    seed: u64,
+    usdc_mint: Pubkey,
+    usdt_mint: Pubkey,
+) -> Result<()> {
+    let program_id = program.id();
+    let escrow = escrow_address(&program_id, &sender.pubkey(), &receiver);
+
+    let signature = program
+        .request()
+        .accounts(accounts::InitializeEscrow {
+            fee_payer: authority.pubkey(),
+            factory: factory_address(&program_id, &authority.pubkey()),
+            escrow,
+            sender: sender.pubkey(),
+            receiver,
+            authority: authority.pubkey(),
+            sender_usdc_ata: get_associated_token_address(&sender.pubkey(), &usdc_mint),
+            sender_usdt_ata: get_associated_token_address(&sender.pubkey(), &usdt_mint),
+            receiver_usdc_ata: get_associated_token_address(&receiver, &usdc_mint),
+            receiver_usdt_ata: get_associated_token_address(&receiver, &usdt_mint),
+            usdc_mint,
+            usdt_mint,
+            vault_usdc: vault_address(&program_id, b"usdc-vault", &escrow, &usdc_mint),
+            vault_usdt: vault_address(&program_id, b"usdt-vault", &escrow, &usdt_mint),
+            associated_token_program: associated_token::ID,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        })
+        .args(instruction::InitializeEscrow { seed })
+        .signer(sender)
+        .send()?;
+
+    println!("escrow: {escrow}");
+    println!("signature: {signature}");
+    Ok(())
+}
+
+pub fn deposit(
+    program: &SendaProgram,
+    authority: &Keypair,
+    escrow_key: Pubkey,
+    depositor: &Keypair,
+    stable: Stable,
+    authorization: AuthorizedBy,
+    amount: u64,
+) -> Result<()> {
+    let escrow: Escrow = program.account(escrow_key)?;
+    let receiver = counterparty(&escrow, depositor.pubkey())?;
+
+    // Any unique 32 bytes work as the record seed, the latest blockhash is what the dapp uses
+    let blockhash = program.rpc().get_latest_blockhash()?;
+    let blockhash_bytes = blockhash.to_bytes();
+    let deposit_record =
+        deposit_record_address(&program.id(), &escrow_key, &depositor.pubkey(), &blockhash_bytes);
+
+    let signature = program
+        .request()
+        .accounts(accounts::Deposit {
+            escrow: escrow_key,
+            sender: depositor.pubkey(),
+            receiver,
+            authority: authority.pubkey(),
+            sender_usdc_ata: get_associated_token_address(&depositor.pubkey(), &escrow.usdc_mint),
+            sender_usdt_ata: get_associated_token_address(&depositor.pubkey(), &escrow.usdt_mint),
+            receiver_usdc_ata: get_associated_token_address(&receiver, &escrow.usdc_mint),
+            receiver_usdt_ata: get_associated_token_address(&receiver, &escrow.usdt_mint),
+            usdc_mint: escrow.usdc_mint,
+            usdt_mint: escrow.usdt_mint,
+            vault_usdc: escrow.vault_usdc,
+            vault_usdt: escrow.vault_usdt,
+            fee_payer: authority.pubkey(),
+            deposit_record,
+            associated_token_program: associated_token::ID,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        })
+        .args(instruction::Deposit {
+            stable,
+            authorization,
+            _recent_blockhash: blockhash_bytes,
+            amount,
+        })
+        .signer(depositor)
+        .send()?;
+
+    println!("deposit record: {deposit_record}");
+    println!("blockhash: {blockhash}");
+    println!("signature: {signature}");
+    Ok(())
+}
+
+pub fn release(
+    program: &SendaProgram,
+    authority: &Keypair,
+    escrow_key: Pubkey,
+    depositor: Pubkey,
+    blockhash: &str,
+    to: Option<Pubkey>,
+    signers: &[Keypair],
+) -> Result<()> {
+    let escrow: Escrow = program.account(escrow_key)?;
+    let receiver = counterparty(&escrow, depositor)?;
+    let receiving_party = to.unwrap_or(receiver);
+    let blockhash = parse_blockhash(blockhash)?;
+
+    let ix = instruction_with_signers(
+        program,
+        accounts::Release {
+            escrow: escrow_key,
+            sender: depositor,
+            receiver,
+            authority: authority.pubkey(),
+            receiving_party,
+            receiving_usdc_ata: get_associated_token_address(&receiving_party, &escrow.usdc_mint),
+            receiving_usdt_ata: get_associated_token_address(&receiving_party, &escrow.usdt_mint),
+            usdc_mint: escrow.usdc_mint,
+            usdt_mint: escrow.usdt_mint,
+            vault_usdc: escrow.vault_usdc,
+            vault_usdt: escrow.vault_usdt,
+            deposit_record: deposit_record_address(&program.id(), &escrow_key, &depositor, &blockhash),
+            associated_token_program: associated_token::ID,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::Release {
+            recent_blockhash: blockhash,
+        },
+        signers,
+    );
+
+    let mut request = program.request().instruction(ix);
+    for signer in signers {
+        request = request.signer(signer);
+    }
+    let signature = request.send()?;
+
+    println!("signature: {signature}");
+    Ok(())
+}
+
+pub fn cancel(
+    program: &SendaProgram,
+    authority: &Keypair,
+    escrow_key: Pubkey,
+    depositor: Pubkey,
+    blockhash: &str,
+    signers: &[Keypair],
+) -> Result<()> {
+    let escrow: Escrow = program.account(escrow_key)?;
+    let receiver = counterparty(&escrow, depositor)?;
+    let blockhash = parse_blockhash(blockhash)?;
+
+    let ix = instruction_with_signers(
+        program,
+        accounts::Cancel {
+            escrow: escrow_key,
+            sender: depositor,
+            receiver,
+            authority: authority.pubkey(),
+            sender_usdc_ata: get_associated_token_address(&depositor, &escrow.usdc_mint),
+            sender_usdt_ata: get_associated_token_address(&depositor, &escrow.usdt_mint),
+            usdc_mint: escrow.usdc_mint,
+            usdt_mint: escrow.usdt_mint,
+            vault_usdc: escrow.vault_usdc,
+            vault_usdt: escrow.vault_usdt,
+            deposit_record: deposit_record_address(&program.id(), &escrow_key, &depositor, &blockhash),
+            associated_token_program: associated_token::ID,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::Cancel {
+            recent_blockhash: blockhash,
+        },
+        signers,
+    );
+
+    let mut request = program.request().instruction(ix);
+    for signer in signers {
+        request = request.signer(signer);
+    }
+    let signature = request.send()?;
+
+    println!("signature: {signature}");
+    Ok(())
+}
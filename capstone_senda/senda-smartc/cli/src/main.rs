@@ -0,0 +1,225 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{read_keypair_file, Keypair};
+use anchor_client::{Client, Cluster};
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use senda_smartc::state::{AuthorizedBy, Stable};
+
+mod commands;
+mod query;
+
+#[derive(Parser)]
+#[command(name = "senda-cli", about = "Admin tool for Senda factories and escrows")]
+struct Cli {
+    /// RPC URL or moniker (localnet, devnet, mainnet)
+    #[arg(long, short = 'u', global = true, default_value = "localnet")]
+    url: String,
+
+    /// Senda authority keypair, also pays fees (defaults to ~/.config/solana/id.json)
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<PathBuf>,
+
+    #[arg(long, global = true, default_value_t = senda_smartc::ID)]
+    program_id: Pubkey,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the factory owned by the authority keypair
+    InitFactory,
+
+    /// Open an escrow between a sender and a receiver
+    CreateEscrow {
+        #[arg(long)]
+        sender_keypair: PathBuf,
+        #[arg(long)]
+        receiver: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        #[arg(long, default_value = senda_smartc::state::USDC_MINT_ADDR)]
+        usdc_mint: Pubkey,
+        #[arg(long, default_value = senda_smartc::state::USDT_MINT_ADDR)]
+        usdt_mint: Pubkey,
+    },
+
+    /// Deposit stables into an escrow from one of its parties
+    Deposit {
+        #[arg(long)]
+        escrow: Pubkey,
+        #[arg(long)]
+        depositor_keypair: PathBuf,
+        #[arg(long, value_enum)]
+        stable: StableArg,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long, value_enum, default_value_t = AuthorizedByArg::Sender)]
+        authorized_by: AuthorizedByArg,
+    },
+
+    /// Release a pending deposit to the counterparty (or --to)
+    Release {
+        #[arg(long)]
+        escrow: Pubkey,
+        #[arg(long)]
+        depositor: Pubkey,
+        /// Blockhash printed by `deposit`, part of the deposit record seeds
+        #[arg(long)]
+        blockhash: String,
+        #[arg(long)]
+        to: Option<Pubkey>,
+        /// Party keypairs required by the deposit's signature policy
+        #[arg(long = "signer")]
+        signers: Vec<PathBuf>,
+    },
+
+    /// Cancel a pending deposit, refunding the depositor
+    Cancel {
+        #[arg(long)]
+        escrow: Pubkey,
+        #[arg(long)]
+        depositor: Pubkey,
+        #[arg(long)]
+        blockhash: String,
+        #[arg(long = "signer")]
+        signers: Vec<PathBuf>,
+    },
+
+    /// List every escrow where the wallet is the sender or the receiver
+    ListEscrows {
+        #[arg(long)]
+        wallet: Pubkey,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+
+    /// Dump the deposit records of an escrow in deposit order
+    History {
+        #[arg(long)]
+        escrow: Pubkey,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StableArg {
+    Usdc,
+    Usdt,
+}
+
+impl From<StableArg> for Stable {
+    fn from(stable: StableArg) -> Self {
+        match stable {
+            StableArg::Usdc => Stable::Usdc,
+            StableArg::Usdt => Stable::Usdt,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AuthorizedByArg {
+    Sender,
+    Receiver,
+    Both,
+}
+
+impl From<AuthorizedByArg> for AuthorizedBy {
+    fn from(authorized_by: AuthorizedByArg) -> Self {
+        match authorized_by {
+            AuthorizedByArg::Sender => AuthorizedBy::Sender,
+            AuthorizedByArg::Receiver => AuthorizedBy::Receiver,
+            AuthorizedByArg::Both => AuthorizedBy::Both,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+pub fn load_keypair(path: &PathBuf) -> Result<Keypair> {
+    read_keypair_file(path).map_err(|e| anyhow!("failed to read keypair {}: {e}", path.display()))
+}
+
+fn default_keypair_path() -> Result<PathBuf> {
+    let home = std::env::var("HOME").map_err(|_| anyhow!("HOME is not set, pass --keypair"))?;
+    Ok(PathBuf::from(home).join(".config/solana/id.json"))
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let keypair_path = match cli.keypair {
+        Some(path) => path,
+        None => default_keypair_path()?,
+    };
+    let authority = Arc::new(load_keypair(&keypair_path)?);
+
+    let cluster = Cluster::from_str(&cli.url)?;
+    let client = Client::new_with_options(cluster, authority.clone(), CommitmentConfig::confirmed());
+    let program = client.program(cli.program_id)?;
+
+    match cli.command {
+        Command::InitFactory => commands::init_factory(&program, &authority),
+        Command::CreateEscrow {
+            sender_keypair,
+            receiver,
+            seed,
+            usdc_mint,
+            usdt_mint,
+        } => {
+            let sender = load_keypair(&sender_keypair)?;
+            commands::create_escrow(&program, &authority, &sender, receiver, seed, usdc_mint, usdt_mint)
+        }
+        Command::Deposit {
+            escrow,
+            depositor_keypair,
+            stable,
+            amount,
+            authorized_by,
+        } => {
+            let depositor = load_keypair(&depositor_keypair)?;
+            commands::deposit(
+                &program,
+                &authority,
+                escrow,
+                &depositor,
+                stable.into(),
+                authorized_by.into(),
+                amount,
+            )
+        }
+        Command::Release {
+            escrow,
+            depositor,
+            blockhash,
+            to,
+            signers,
+        } => {
+            let signers = signers.iter().map(load_keypair).collect::<Result<Vec<_>>>()?;
+            commands::release(&program, &authority, escrow, depositor, &blockhash, to, &signers)
+        }
+        Command::Cancel {
+            escrow,
+            depositor,
+            blockhash,
+            signers,
+        } => {
+            let signers = signers.iter().map(load_keypair).collect::<Result<Vec<_>>>()?;
+            commands::cancel(&program, &authority, escrow, depositor, &blockhash, &signers)
+        }
+        Command::ListEscrows { wallet, format } => query::list_escrows(&program, wallet, format),
+        Command::History { escrow, format } => query::history(&program, escrow, format),
+    }
+}
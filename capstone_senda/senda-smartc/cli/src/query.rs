@@ -0,0 +1,245 @@
+use std::io;
+
+use anchor_client::anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_client::solana_account_decoder::UiAccountEncoding;
+use anchor_client::solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::Result;
+use serde::Serialize;
+
+use senda_smartc::state::{
+    CancelPolicy, DepositRecord, DepositState, Escrow, EscrowState, SignaturePolicy, Stable,
+};
+
+use crate::commands::SendaProgram;
+use crate::Format;
+
+// Byte offsets into the account data, discriminator included. They follow the
+// field order in `state.rs` and must move with it, the tests below catch a drift.
+const ESCROW_SENDER_OFFSET: usize = 8 + 1 + 8;
+const ESCROW_RECEIVER_OFFSET: usize = ESCROW_SENDER_OFFSET + 32;
+const DEPOSIT_RECORD_ESCROW_OFFSET: usize = 8 + 1;
+
+#[derive(Serialize)]
+struct EscrowRow {
+    address: String,
+    sender: String,
+    receiver: String,
+    authority: String,
+    state: &'static str,
+    deposited_usdc: u64,
+    deposited_usdt: u64,
+    deposit_count: u64,
+}
+
+impl EscrowRow {
+    fn new(address: Pubkey, escrow: &Escrow) -> Self {
+        Self {
+            address: address.to_string(),
+            sender: escrow.sender.to_string(),
+            receiver: escrow.receiver.to_string(),
+            authority: escrow.authority.to_string(),
+            state: match escrow.state {
+                EscrowState::Active => "active",
+                EscrowState::Closed => "closed",
+            },
+            deposited_usdc: escrow.deposited_usdc,
+            deposited_usdt: escrow.deposited_usdt,
+            deposit_count: escrow.deposit_count,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DepositRow {
+    address: String,
+    deposit_idx: u64,
+    stable: &'static str,
+    amount: u64,
+    state: &'static str,
+    policy: String,
+    cancel_policy: &'static str,
+}
+
+impl DepositRow {
+    fn new(address: Pubkey, record: &DepositRecord) -> Self {
+        Self {
+            address: address.to_string(),
+            deposit_idx: record.deposit_idx,
+            stable: match record.stable {
+                Stable::Usdc => "usdc",
+                Stable::Usdt => "usdt",
+            },
+            amount: record.amount,
+            state: match record.state {
+                DepositState::PendingWithdrawal => "pending_withdrawal",
+                DepositState::Complete => "complete",
+                DepositState::Disputed => "disputed",
+                DepositState::Cancelled => "cancelled",
+            },
+            policy: match record.policy {
+                SignaturePolicy::Dual => "dual".to_string(),
+                SignaturePolicy::Single { signer } => format!("single:{signer}"),
+            },
+            cancel_policy: match record.cancel_policy {
+                CancelPolicy::SenderOnly => "sender_only",
+                CancelPolicy::Mutual => "mutual",
+            },
+        }
+    }
+}
+
+fn write_rows<T: Serialize>(rows: &[T], format: Format) -> Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout(), rows)?;
+            println!();
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+// Like `Program::accounts`, but an account that does not decode, such as one
+// still on a legacy layout, is reported on stderr and skipped instead of failing
+// the whole listing
+fn decodable_accounts<T: AccountDeserialize + Discriminator>(
+    program: &SendaProgram,
+    filters: Vec<RpcFilterType>,
+) -> Result<Vec<(Pubkey, T)>> {
+    let mut all_filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, T::DISCRIMINATOR))];
+    all_filters.extend(filters);
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(all_filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = program.rpc().get_program_accounts_with_config(&program.id(), config)?;
+
+    let decoded = accounts
+        .into_iter()
+        .filter_map(|(address, account)| match T::try_deserialize(&mut &account.data[..]) {
+            Ok(value) => Some((address, value)),
+            Err(err) => {
+                eprintln!("skipping {address}, it does not decode (unmigrated?): {err}");
+                None
+            }
+        })
+        .collect();
+
+    Ok(decoded)
+}
+
+pub fn list_escrows(program: &SendaProgram, wallet: Pubkey, format: Format) -> Result<()> {
+    let mut escrows = Vec::new();
+
+    for offset in [ESCROW_SENDER_OFFSET, ESCROW_RECEIVER_OFFSET] {
+        let filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            offset,
+            wallet.as_ref(),
+        ))];
+        escrows.extend(decodable_accounts::<Escrow>(program, filters)?);
+    }
+
+    // An escrow a wallet opened with itself would match both filters
+    escrows.sort_by_key(|(address, _)| *address);
+    escrows.dedup_by_key(|(address, _)| *address);
+
+    let rows: Vec<EscrowRow> = escrows
+        .iter()
+        .map(|(address, escrow)| EscrowRow::new(*address, escrow))
+        .collect();
+
+    write_rows(&rows, format)
+}
+
+pub fn history(program: &SendaProgram, escrow: Pubkey, format: Format) -> Result<()> {
+    let filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        DEPOSIT_RECORD_ESCROW_OFFSET,
+        escrow.as_ref(),
+    ))];
+    let mut records = decodable_accounts::<DepositRecord>(program, filters)?;
+    records.sort_by_key(|(_, record)| record.deposit_idx);
+
+    let rows: Vec<DepositRow> = records
+        .iter()
+        .map(|(address, record)| DepositRow::new(*address, record))
+        .collect();
+
+    write_rows(&rows, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::anchor_lang::AccountSerialize;
+    use senda_smartc::state::{DEPOSIT_RECORD_VERSION, ESCROW_VERSION};
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn key_at(data: &[u8], offset: usize) -> Pubkey {
+        Pubkey::try_from(&data[offset..offset + 32]).unwrap()
+    }
+
+    #[test]
+    fn escrow_offsets_point_at_the_parties() {
+        let escrow = Escrow {
+            version: ESCROW_VERSION,
+            seed: u64::MAX,
+            sender: Pubkey::new_unique(),
+            receiver: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            usdc_mint: Pubkey::new_unique(),
+            usdt_mint: Pubkey::new_unique(),
+            vault_usdc: Pubkey::new_unique(),
+            vault_usdt: Pubkey::new_unique(),
+            bump: 255,
+            vault_usdc_bump: 255,
+            vault_usdt_bump: 255,
+            deposited_usdc: 0,
+            deposited_usdt: 0,
+            deposit_count: 0,
+            pending_count: 0,
+            state: EscrowState::Active,
+            reserved: [0; 120],
+        };
+        let data = serialize(&escrow);
+
+        assert_eq!(key_at(&data, ESCROW_SENDER_OFFSET), escrow.sender);
+        assert_eq!(key_at(&data, ESCROW_RECEIVER_OFFSET), escrow.receiver);
+    }
+
+    #[test]
+    fn deposit_record_offset_points_at_the_escrow() {
+        let record = DepositRecord {
+            version: DEPOSIT_RECORD_VERSION,
+            escrow: Pubkey::new_unique(),
+            deposit_idx: u64::MAX,
+            amount: u64::MAX,
+            policy: SignaturePolicy::Dual,
+            bump: 255,
+            stable: Stable::Usdt,
+            state: DepositState::PendingWithdrawal,
+            cancel_policy: CancelPolicy::SenderOnly,
+            reserved: [0; 64],
+        };
+        let data = serialize(&record);
+
+        assert_eq!(key_at(&data, DEPOSIT_RECORD_ESCROW_OFFSET), record.escrow);
+    }
+}
@@ -37,4 +37,39 @@ pub enum ErrorCode {
     
     #[msg("Deposit error: Deposit has already been processed")]
     DepositAlreadyProcessed,
+
+    #[msg("Deposit error: Deposit has already been accepted")]
+    DepositAlreadyAccepted,
+
+    #[msg("Deposit error: Deposits to net must be in opposite directions and of the same stable")]
+    NettingMismatch,
+
+    #[msg("Deposit error: Deposit amount must be greater than zero")]
+    ZeroDepositAmount,
+    
+    // === Accounting Errors ===
+    #[msg("Accounting error: Deposited total would overflow")]
+    DepositOverflow,
+
+    #[msg("Accounting error: Deposit counter would overflow")]
+    DepositCountOverflow,
+
+    #[msg("Accounting error: Withdrawal exceeds the deposited total")]
+    WithdrawalUnderflow,
+
+    #[msg("Accounting error: Deposited totals do not match pending deposit records")]
+    LedgerMismatch,
+
+    #[msg("Accounting error: Deposited totals exceed vault balances")]
+    VaultBalanceMismatch,
+
+    #[msg("Accounting error: Deposit records provided are incomplete or duplicated")]
+    IncompleteDepositRecords,
+
+    // === Migration Errors ===
+    #[msg("Migration error: Account does not match a known layout")]
+    InvalidAccountLayout,
+
+    #[msg("Migration error: Account is already on the current layout")]
+    AlreadyMigrated,
 }
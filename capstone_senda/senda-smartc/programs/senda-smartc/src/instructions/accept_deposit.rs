@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::state::{CancelPolicy, DepositRecord, DepositState, Escrow};
+
+#[derive(Accounts)]
+#[instruction(recent_blockhash: [u8; 32])]
+pub struct AcceptDeposit<'info> {
+    #[account(
+        seeds = [b"escrow", escrow.sender.as_ref(), escrow.receiver.as_ref()],
+        bump = escrow.bump,
+        constraint = (
+            (sender.key() == escrow.sender && receiver.key() == escrow.receiver) ||
+            (sender.key() == escrow.receiver && receiver.key() == escrow.sender)
+        ) @ ErrorCode::InvalidParties
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    /// CHECK: Depositor of the deposit being accepted
+    pub sender: AccountInfo<'info>,
+
+    pub receiver: Signer<'info>,
+
+    #[account(
+        constraint = authority.key() == escrow.authority @ ErrorCode::InvalidAuthority
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = deposit_record.escrow == escrow.key() @ ErrorCode::InvalidState,
+        constraint = deposit_record.state == DepositState::PendingWithdrawal @ ErrorCode::InvalidState,
+        seeds = [
+            b"deposit",
+            escrow.key().as_ref(),
+            sender.key().as_ref(),
+            recent_blockhash.as_ref()
+        ],
+        bump = deposit_record.bump,
+    )]
+    pub deposit_record: Account<'info, DepositRecord>,
+}
+
+impl<'info> AcceptDeposit<'info> {
+    pub fn accept_deposit(&mut self, _recent_blockhash: [u8; 32]) -> Result<()> {
+        require!(
+            self.deposit_record.cancel_policy == CancelPolicy::SenderOnly,
+            ErrorCode::DepositAlreadyAccepted
+        );
+
+        // From here on the depositor can no longer reclaim the funds on their own
+        self.deposit_record.cancel_policy = CancelPolicy::Mutual;
+
+        Ok(())
+    }
+}
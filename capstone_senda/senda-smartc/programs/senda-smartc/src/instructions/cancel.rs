@@ -6,19 +6,28 @@ use anchor_spl::{
 };
 
 use crate::error::ErrorCode;
+use crate::ledger;
 use crate::state::{Escrow, DepositState, DepositRecord, Stable};
 
 #[derive(Accounts)]
 #[instruction(recent_blockhash: [u8; 32])]
 pub struct Cancel<'info> {
-    #[account(mut, seeds = [b"escrow", escrow.sender.as_ref(), escrow.receiver.as_ref()], bump = escrow.bump)]
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.sender.as_ref(), escrow.receiver.as_ref()],
+        bump = escrow.bump,
+        constraint = (
+            (sender.key() == escrow.sender && receiver.key() == escrow.receiver) ||
+            (sender.key() == escrow.receiver && receiver.key() == escrow.sender)
+        ) @ ErrorCode::InvalidParties
+    )]
     pub escrow: Box<Account<'info, Escrow>>,
 
-    /// CHECK: Sender of the escrow
+    /// CHECK: Depositor of the deposit being cancelled
     #[account(mut)]
     pub sender: AccountInfo<'info>,
 
-    /// CHECK: Receiver of the escrow
+    /// CHECK: Counterparty of the depositor
     #[account(mut)]
     pub receiver: AccountInfo<'info>,
 
@@ -95,9 +104,10 @@ impl<'info> Cancel<'info> {
             ErrorCode::InvalidState
         );
 
-        // Only the original depositor (sender) can cancel
         require!(
-            self.sender.is_signer,
+            self.deposit_record
+                .cancel_policy
+                .allows(self.sender.is_signer, self.receiver.is_signer),
             ErrorCode::InvalidSigner
         );
         
@@ -144,16 +154,7 @@ impl<'info> Cancel<'info> {
         );
         transfer_checked(cpi_ctx, amount, decimals)?;
 
-        match self.deposit_record.stable {
-            Stable::Usdc => {
-                self.escrow.deposited_usdc = self.escrow.deposited_usdc.checked_sub(amount).unwrap_or(0);
-            },
-            Stable::Usdt => {
-                self.escrow.deposited_usdt = self.escrow.deposited_usdt.checked_sub(amount).unwrap_or(0);
-            },
-        }
-
-        self.deposit_record.state = DepositState::Cancelled;
+        ledger::settle_deposit(&mut self.escrow, &mut self.deposit_record, DepositState::Cancelled)?;
         
         Ok(())
     }
@@ -7,7 +7,8 @@ use anchor_spl::{
 };
 
 use crate::error::ErrorCode;
-use crate::state::{Escrow, DepositRecord, DepositState, Stable, USDC_MINT_ADDR, USDT_MINT_ADDR, EscrowState, AuthorizedBy};
+use crate::ledger;
+use crate::state::{Escrow, DepositRecord, DepositState, Stable, USDC_MINT_ADDR, USDT_MINT_ADDR, EscrowState, AuthorizedBy, DEPOSIT_RECORD_VERSION};
 
 #[derive(Accounts)]
 #[instruction(stable: Stable, authorization: AuthorizedBy, recent_blockhash: [u8; 32])]
@@ -129,20 +130,23 @@ impl<'info> Deposit<'info> {
            ErrorCode::InvalidState
         );
 
-        let escrow = &mut self.escrow;
-        let deposit_idx = escrow.deposit_count;
+        let deposit_idx = ledger::record_deposit(&mut self.escrow, &stable, amount)?;
 
         // signature policy based on authorization
-        let policy = authorization.to_policy(escrow.sender, escrow.receiver);
+        let policy = authorization.to_policy(self.escrow.sender, self.escrow.receiver);
+        let cancel_policy = policy.initial_cancel_policy();
 
         self.deposit_record.set_inner(DepositRecord {
-            escrow: escrow.key(),
+            version: DEPOSIT_RECORD_VERSION,
+            escrow: self.escrow.key(),
             deposit_idx,
             amount,
             policy,
             bump: bump.deposit_record,
             stable: stable.clone(),
             state: DepositState::PendingWithdrawal,
+            cancel_policy,
+            reserved: [0; 64],
         });
 
         let (from_account, mint_info, to_info, decimals) = match stable {
@@ -169,12 +173,6 @@ impl<'info> Deposit<'info> {
         let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
         transfer_checked(cpi_ctx, amount, decimals)?;
 
-        match stable {
-            Stable::Usdc => self.escrow.deposited_usdc += amount,
-            Stable::Usdt => self.escrow.deposited_usdt += amount,
-        }
-        self.escrow.deposit_count += 1;
-
         Ok(())
     }
 }
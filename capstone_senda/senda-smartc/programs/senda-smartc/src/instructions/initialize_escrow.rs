@@ -7,7 +7,7 @@ use anchor_spl::{
 };
 
 use crate::error::ErrorCode;
-use crate::state::{Escrow, EscrowState, Factory, USDC_MINT_ADDR, USDT_MINT_ADDR};
+use crate::state::{Escrow, EscrowState, Factory, ESCROW_VERSION, USDC_MINT_ADDR, USDT_MINT_ADDR};
 
 #[derive(Accounts)]
 pub struct InitializeEscrow<'info> {
@@ -119,6 +119,7 @@ pub struct InitializeEscrow<'info> {
 impl<'info> InitializeEscrow<'info> {
     pub fn init_escrow(&mut self, seed: u64, bump: &InitializeEscrowBumps) -> Result<()> {
         self.escrow.set_inner(Escrow{
+            version: ESCROW_VERSION,
             seed,
             sender: self.sender.key(),
             receiver: self.receiver.key(),
@@ -133,7 +134,9 @@ impl<'info> InitializeEscrow<'info> {
             deposited_usdc: 0,
             deposited_usdt: 0,
             deposit_count: 0,
+            pending_count: 0,
             state: EscrowState::Active,
+            reserved: [0; 120],
         });
 
         Ok(())
@@ -1,4 +1,4 @@
-use crate::state::Factory;
+use crate::state::{Factory, FACTORY_VERSION};
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -32,11 +32,13 @@ impl<'info> InitializeFactory<'info> {
         let mint_auth_bump = bumps.mint_authority;
 
         self.factory.set_inner(Factory {
+            version: FACTORY_VERSION,
             admin: self.authority.key(),
             factory_bump,
             mint_authority: self.mint_authority.key(),
             mint_auth_bump,
             escrow_count: 0,
+            reserved: [0; 64],
         });
 
         Ok(())
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::ledger;
+use crate::migration::{self, DepositRecordV0};
+use crate::state::{DepositRecord, Escrow};
+
+#[derive(Accounts)]
+pub struct MigrateDepositRecord<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: legacy deposit record layout, validated by `migration::read_legacy`
+    #[account(mut)]
+    pub deposit_record: UncheckedAccount<'info>,
+
+    // already migrated, a legacy escrow does not deserialize
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateDepositRecord<'info> {
+    // A pending record is added back to its escrow's totals as it is migrated.
+    // Records migrate one at a time, so an escrow with any number of open
+    // deposits can be migrated over as many transactions as it takes.
+    pub fn migrate_deposit_record(&mut self) -> Result<()> {
+        let legacy: DepositRecordV0 = migration::read_legacy::<_, DepositRecord>(
+            &self.deposit_record,
+            DepositRecordV0::INIT_SPACE,
+            DepositRecord::INIT_SPACE,
+        )?;
+
+        let upgraded = legacy.upgrade();
+        require_keys_eq!(upgraded.escrow, self.escrow.key(), ErrorCode::InvalidState);
+
+        ledger::adopt_pending(&mut self.escrow, &upgraded)?;
+
+        migration::write_upgraded(
+            &self.deposit_record,
+            &self.payer,
+            &self.system_program,
+            DepositRecord::INIT_SPACE,
+            &upgraded,
+        )
+    }
+}
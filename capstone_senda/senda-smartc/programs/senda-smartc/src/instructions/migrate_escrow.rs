@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::migration::{self, EscrowV0};
+use crate::state::Escrow;
+
+#[derive(Accounts)]
+pub struct MigrateEscrow<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: legacy escrow layout, validated by `migration::read_legacy`
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateEscrow<'info> {
+    // Migrate the escrow before its deposit records, which add themselves back
+    // to its totals as they are migrated
+    pub fn migrate_escrow(&mut self) -> Result<()> {
+        let legacy: EscrowV0 = migration::read_legacy::<_, Escrow>(
+            &self.escrow,
+            EscrowV0::INIT_SPACE,
+            Escrow::INIT_SPACE,
+        )?;
+
+        let upgraded = legacy.upgrade();
+
+        migration::write_upgraded(
+            &self.escrow,
+            &self.payer,
+            &self.system_program,
+            Escrow::INIT_SPACE,
+            &upgraded,
+        )
+    }
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::migration::{self, FactoryV0};
+use crate::state::Factory;
+
+#[derive(Accounts)]
+pub struct MigrateFactory<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: legacy factory layout, validated by `migration::read_legacy`
+    #[account(mut)]
+    pub factory: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateFactory<'info> {
+    pub fn migrate_factory(&mut self) -> Result<()> {
+        let legacy: FactoryV0 = migration::read_legacy::<_, Factory>(
+            &self.factory,
+            FactoryV0::INIT_SPACE,
+            Factory::INIT_SPACE,
+        )?;
+
+        let upgraded = legacy.upgrade();
+
+        migration::write_upgraded(
+            &self.factory,
+            &self.payer,
+            &self.system_program,
+            Factory::INIT_SPACE,
+            &upgraded,
+        )
+    }
+}
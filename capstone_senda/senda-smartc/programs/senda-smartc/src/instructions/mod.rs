@@ -3,9 +3,21 @@ pub mod initialize_escrow;
 pub mod deposit;
 pub mod release;
 pub mod cancel;
+pub mod accept_deposit;
+pub mod net_settle;
+pub mod verify_escrow_invariants;
+pub mod migrate_factory;
+pub mod migrate_escrow;
+pub mod migrate_deposit_record;
 
 pub use initialize_factory::*;
 pub use initialize_escrow::*;
 pub use deposit::*;
 pub use release::*;
-pub use cancel::*;
\ No newline at end of file
+pub use cancel::*;
+pub use accept_deposit::*;
+pub use net_settle::*;
+pub use verify_escrow_invariants::*;
+pub use migrate_factory::*;
+pub use migrate_escrow::*;
+pub use migrate_deposit_record::*;
\ No newline at end of file
@@ -0,0 +1,184 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{transfer_checked, TransferChecked, Token, Mint as SplMint, TokenAccount as SplTokenAccount},
+};
+
+use crate::error::ErrorCode;
+use crate::ledger;
+use crate::state::{Escrow, EscrowState, DepositState, DepositRecord, Stable};
+
+// Settles a deposit A owes B against one B owes A, both pending in the same
+// stable, and marks the two records complete. Each deposit sits in its own
+// escrow's vault, so the settlement is up to three transfers: the larger
+// deposit's vault pays the difference to its counterparty and each vault hands
+// the offsetting amount back to its own depositor. Net of their deposits, only
+// the difference changes hands.
+#[derive(Accounts)]
+#[instruction(stable: Stable, blockhash_ab: [u8; 32], blockhash_ba: [u8; 32])]
+pub struct NetSettle<'info> {
+    // Escrow where A is the sender and B the receiver
+    #[account(
+        mut,
+        seeds = [b"escrow", party_a.key().as_ref(), party_b.key().as_ref()],
+        bump = escrow_ab.bump,
+        constraint = escrow_ab.state == EscrowState::Active @ ErrorCode::InvalidState,
+    )]
+    pub escrow_ab: Box<Account<'info, Escrow>>,
+
+    // Escrow in the opposite direction, B to A
+    #[account(
+        mut,
+        seeds = [b"escrow", party_b.key().as_ref(), party_a.key().as_ref()],
+        bump = escrow_ba.bump,
+        constraint = escrow_ba.state == EscrowState::Active @ ErrorCode::InvalidState,
+    )]
+    pub escrow_ba: Box<Account<'info, Escrow>>,
+
+    #[account(mut)]
+    pub party_a: Signer<'info>,
+
+    #[account(mut)]
+    pub party_b: Signer<'info>,
+
+    #[account(
+        constraint = authority.key() == escrow_ab.authority @ ErrorCode::InvalidAuthority,
+        constraint = authority.key() == escrow_ba.authority @ ErrorCode::InvalidAuthority
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program,
+        constraint = mint.key() == escrow_ab.mint(&stable) @ ErrorCode::NettingMismatch,
+        constraint = mint.key() == escrow_ba.mint(&stable) @ ErrorCode::NettingMismatch
+    )]
+    pub mint: Box<Account<'info, SplMint>>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = escrow_ab,
+        seeds = [stable.vault_seed(), escrow_ab.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_ab: Box<Account<'info, SplTokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = escrow_ba,
+        seeds = [stable.vault_seed(), escrow_ba.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_ba: Box<Account<'info, SplTokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = party_a,
+    )]
+    pub party_a_ata: Box<Account<'info, SplTokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = party_b,
+    )]
+    pub party_b_ata: Box<Account<'info, SplTokenAccount>>,
+
+    // Deposited by A into escrow_ab, what A owes B
+    #[account(
+        mut,
+        constraint = deposit_ab.escrow == escrow_ab.key() @ ErrorCode::InvalidState,
+        constraint = deposit_ab.state == DepositState::PendingWithdrawal @ ErrorCode::InvalidState,
+        constraint = deposit_ab.stable == stable @ ErrorCode::NettingMismatch,
+        seeds = [
+            b"deposit",
+            escrow_ab.key().as_ref(),
+            party_a.key().as_ref(),
+            blockhash_ab.as_ref()
+        ],
+        bump = deposit_ab.bump,
+    )]
+    pub deposit_ab: Box<Account<'info, DepositRecord>>,
+
+    // Deposited by B into escrow_ba, what B owes A
+    #[account(
+        mut,
+        constraint = deposit_ba.escrow == escrow_ba.key() @ ErrorCode::InvalidState,
+        constraint = deposit_ba.state == DepositState::PendingWithdrawal @ ErrorCode::InvalidState,
+        constraint = deposit_ba.stable == stable @ ErrorCode::NettingMismatch,
+        seeds = [
+            b"deposit",
+            escrow_ba.key().as_ref(),
+            party_b.key().as_ref(),
+            blockhash_ba.as_ref()
+        ],
+        bump = deposit_ba.bump,
+    )]
+    pub deposit_ba: Box<Account<'info, DepositRecord>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> NetSettle<'info> {
+    pub fn net_settle(&mut self, _blockhash_ab: [u8; 32], _blockhash_ba: [u8; 32]) -> Result<()> {
+        let owed_to_b = self.deposit_ab.amount;
+        let owed_to_a = self.deposit_ba.amount;
+
+        // difference first, then the offsetting amounts back out of both vaults
+        if owed_to_b >= owed_to_a {
+            self.pay_from_vault(true, self.party_b_ata.to_account_info(), owed_to_b - owed_to_a)?;
+            self.pay_from_vault(true, self.party_a_ata.to_account_info(), owed_to_a)?;
+            self.pay_from_vault(false, self.party_b_ata.to_account_info(), owed_to_a)?;
+        } else {
+            self.pay_from_vault(false, self.party_a_ata.to_account_info(), owed_to_a - owed_to_b)?;
+            self.pay_from_vault(false, self.party_b_ata.to_account_info(), owed_to_b)?;
+            self.pay_from_vault(true, self.party_a_ata.to_account_info(), owed_to_b)?;
+        }
+
+        ledger::settle_deposit(&mut self.escrow_ab, &mut self.deposit_ab, DepositState::Complete)?;
+        ledger::settle_deposit(&mut self.escrow_ba, &mut self.deposit_ba, DepositState::Complete)?;
+
+        Ok(())
+    }
+
+    fn pay_from_vault(&self, from_ab: bool, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (escrow, vault) = if from_ab {
+            (&self.escrow_ab, &self.vault_ab)
+        } else {
+            (&self.escrow_ba, &self.vault_ba)
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: vault.to_account_info(),
+            mint: self.mint.to_account_info(),
+            to,
+            authority: escrow.to_account_info(),
+        };
+
+        let escrow_seeds: [&[u8]; 4] = [
+            b"escrow".as_ref(),
+            escrow.sender.as_ref(),
+            escrow.receiver.as_ref(),
+            &[escrow.bump],
+        ];
+        let seeds_slice: &[&[u8]] = &escrow_seeds;
+        let signer_seeds = &[seeds_slice];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_ctx, amount, self.mint.decimals)
+    }
+}
@@ -6,6 +6,7 @@ use anchor_spl::{
 };
 
 use crate::error::ErrorCode;
+use crate::ledger;
 use crate::state::{Escrow, DepositState, DepositRecord, Stable, SignaturePolicy};
 
 #[derive(Accounts)]
@@ -85,7 +86,7 @@ pub struct Release<'info> {
             sender.key().as_ref(),
             recent_blockhash.as_ref()
         ],
-        bump,
+        bump = deposit_record.bump,
     )]
     pub deposit_record: Account<'info, DepositRecord>,
 
@@ -162,12 +163,7 @@ impl<'info> Release<'info> {
 
         transfer_checked(cpi_ctx, amount, decimals)?;
 
-        match stable {
-            Stable::Usdc => self.escrow.deposited_usdc = self.escrow.deposited_usdc.checked_sub(amount).unwrap(),
-            Stable::Usdt => self.escrow.deposited_usdt = self.escrow.deposited_usdt.checked_sub(amount).unwrap(),
-        }
-
-        self.deposit_record.state = DepositState::Complete;
+        ledger::settle_deposit(&mut self.escrow, &mut self.deposit_record, DepositState::Complete)?;
 
         Ok(())
     }
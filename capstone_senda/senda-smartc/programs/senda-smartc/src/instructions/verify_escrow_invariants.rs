@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token::TokenAccount as SplTokenAccount;
+
+use crate::ledger;
+use crate::state::Escrow;
+
+#[derive(Accounts)]
+pub struct VerifyEscrowInvariants<'info> {
+    #[account(seeds = [b"escrow", escrow.sender.as_ref(), escrow.receiver.as_ref()], bump = escrow.bump)]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    #[account(
+        token::mint = escrow.usdc_mint,
+        token::authority = escrow,
+        seeds = [b"usdc-vault", escrow.key().as_ref(), escrow.usdc_mint.as_ref()],
+        bump = escrow.vault_usdc_bump,
+    )]
+    pub vault_usdc: Box<Account<'info, SplTokenAccount>>,
+
+    #[account(
+        token::mint = escrow.usdt_mint,
+        token::authority = escrow,
+        seeds = [b"usdt-vault", escrow.key().as_ref(), escrow.usdt_mint.as_ref()],
+        bump = escrow.vault_usdt_bump,
+    )]
+    pub vault_usdt: Box<Account<'info, SplTokenAccount>>,
+}
+
+impl<'info> VerifyEscrowInvariants<'info> {
+    // Every pending deposit record of the escrow must be passed as a remaining
+    // account, otherwise pending amounts could be hidden from the sum. Settled
+    // records are left out, so the check stays within one transaction.
+    pub fn verify(&self, deposit_records: &[AccountInfo]) -> Result<()> {
+        let pending = ledger::sum_pending(&self.escrow.key(), deposit_records)?;
+
+        ledger::check_invariants(
+            &self.escrow,
+            &pending,
+            self.vault_usdc.amount,
+            self.vault_usdt.amount,
+        )
+    }
+}
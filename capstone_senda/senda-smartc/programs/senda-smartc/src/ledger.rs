@@ -0,0 +1,291 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::state::{DepositRecord, DepositState, Escrow, Stable};
+
+// All changes to `Escrow.deposited_*`, `Escrow.deposit_count` and
+// `Escrow.pending_count` go through here so the totals always match the pending
+// deposit records backing them.
+
+pub fn record_deposit(escrow: &mut Escrow, stable: &Stable, amount: u64) -> Result<u64> {
+    // an empty record would be counted as pending without adding to the totals
+    require!(amount > 0, ErrorCode::ZeroDepositAmount);
+
+    let deposit_idx = escrow.deposit_count;
+    escrow.deposit_count = deposit_idx
+        .checked_add(1)
+        .ok_or(ErrorCode::DepositCountOverflow)?;
+
+    add_pending(escrow, stable, amount)?;
+
+    Ok(deposit_idx)
+}
+
+// Takes on a deposit record migrated from the legacy layout. Migrated escrows
+// start from empty totals and are rebuilt from their pending records one by
+// one, so the legacy totals are never trusted.
+pub fn adopt_pending(escrow: &mut Escrow, record: &DepositRecord) -> Result<()> {
+    if record.state != DepositState::PendingWithdrawal {
+        return Ok(());
+    }
+
+    add_pending(escrow, &record.stable, record.amount)
+}
+
+fn add_pending(escrow: &mut Escrow, stable: &Stable, amount: u64) -> Result<()> {
+    let total = match stable {
+        Stable::Usdc => &mut escrow.deposited_usdc,
+        Stable::Usdt => &mut escrow.deposited_usdt,
+    };
+    *total = total.checked_add(amount).ok_or(ErrorCode::DepositOverflow)?;
+
+    escrow.pending_count = escrow
+        .pending_count
+        .checked_add(1)
+        .ok_or(ErrorCode::DepositCountOverflow)?;
+
+    Ok(())
+}
+
+pub fn settle_deposit(
+    escrow: &mut Escrow,
+    record: &mut DepositRecord,
+    outcome: DepositState,
+) -> Result<()> {
+    require!(
+        record.state == DepositState::PendingWithdrawal,
+        ErrorCode::DepositAlreadyProcessed
+    );
+    require!(
+        outcome == DepositState::Complete || outcome == DepositState::Cancelled,
+        ErrorCode::InvalidState
+    );
+
+    let total = match record.stable {
+        Stable::Usdc => &mut escrow.deposited_usdc,
+        Stable::Usdt => &mut escrow.deposited_usdt,
+    };
+    *total = total
+        .checked_sub(record.amount)
+        .ok_or(ErrorCode::WithdrawalUnderflow)?;
+    escrow.pending_count = escrow
+        .pending_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::LedgerMismatch)?;
+
+    record.state = outcome;
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct PendingTotals {
+    pub usdc: u64,
+    pub usdt: u64,
+    pub count: u64,
+}
+
+impl PendingTotals {
+    pub fn add(&mut self, record: &DepositRecord) -> Result<()> {
+        require!(
+            record.state == DepositState::PendingWithdrawal,
+            ErrorCode::InvalidState
+        );
+
+        let total = match record.stable {
+            Stable::Usdc => &mut self.usdc,
+            Stable::Usdt => &mut self.usdt,
+        };
+        *total = total
+            .checked_add(record.amount)
+            .ok_or(ErrorCode::DepositOverflow)?;
+        self.count += 1;
+
+        Ok(())
+    }
+}
+
+// Sums the escrow's pending deposit records passed as remaining accounts. They
+// must be listed by increasing deposit_idx, which also rules out duplicates.
+pub fn sum_pending(escrow: &Pubkey, deposit_records: &[AccountInfo]) -> Result<PendingTotals> {
+    let mut pending = PendingTotals::default();
+    let mut last_idx: Option<u64> = None;
+
+    for info in deposit_records {
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidState);
+        let record = DepositRecord::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+
+        require_keys_eq!(record.escrow, *escrow, ErrorCode::InvalidState);
+        if let Some(idx) = last_idx {
+            require!(record.deposit_idx > idx, ErrorCode::IncompleteDepositRecords);
+        }
+        last_idx = Some(record.deposit_idx);
+
+        pending.add(&record)?;
+    }
+
+    Ok(pending)
+}
+
+// Tokens sent straight to a vault are surplus the ledger does not track, so the
+// vaults only have to cover the deposited totals.
+pub fn check_invariants(
+    escrow: &Escrow,
+    pending: &PendingTotals,
+    vault_usdc_amount: u64,
+    vault_usdt_amount: u64,
+) -> Result<()> {
+    require!(
+        pending.count == escrow.pending_count,
+        ErrorCode::IncompleteDepositRecords
+    );
+    require!(
+        escrow.deposited_usdc == pending.usdc && escrow.deposited_usdt == pending.usdt,
+        ErrorCode::LedgerMismatch
+    );
+    require!(
+        vault_usdc_amount >= escrow.deposited_usdc && vault_usdt_amount >= escrow.deposited_usdt,
+        ErrorCode::VaultBalanceMismatch
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{CancelPolicy, EscrowState, SignaturePolicy};
+
+    fn escrow() -> Escrow {
+        Escrow {
+            version: 1,
+            seed: 0,
+            sender: Pubkey::new_unique(),
+            receiver: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            usdc_mint: Pubkey::new_unique(),
+            usdt_mint: Pubkey::new_unique(),
+            vault_usdc: Pubkey::new_unique(),
+            vault_usdt: Pubkey::new_unique(),
+            bump: 255,
+            vault_usdc_bump: 255,
+            vault_usdt_bump: 255,
+            deposited_usdc: 0,
+            deposited_usdt: 0,
+            deposit_count: 0,
+            pending_count: 0,
+            state: EscrowState::Active,
+            reserved: [0; 120],
+        }
+    }
+
+    fn deposit(escrow_key: Pubkey, escrow: &mut Escrow, stable: Stable, amount: u64) -> DepositRecord {
+        let deposit_idx = record_deposit(escrow, &stable, amount).unwrap();
+
+        DepositRecord {
+            version: 1,
+            escrow: escrow_key,
+            deposit_idx,
+            amount,
+            policy: SignaturePolicy::Dual,
+            bump: 255,
+            stable,
+            state: DepositState::PendingWithdrawal,
+            cancel_policy: CancelPolicy::Mutual,
+            reserved: [0; 64],
+        }
+    }
+
+    fn serialize(record: &DepositRecord) -> Vec<u8> {
+        let mut data = Vec::new();
+        record.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    // Runs `sum_pending` over the records as program-owned accounts
+    fn sum(escrow_key: &Pubkey, records: &[&DepositRecord]) -> Result<PendingTotals> {
+        let keys: Vec<Pubkey> = records.iter().map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = vec![0u64; records.len()];
+        let mut data: Vec<Vec<u8>> = records.iter().map(|record| serialize(record)).collect();
+
+        let infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, false, lamports, data, &crate::ID, false, 0)
+            })
+            .collect();
+
+        sum_pending(escrow_key, &infos)
+    }
+
+    #[test]
+    fn settling_keeps_totals_on_pending_records() {
+        let key = Pubkey::new_unique();
+        let mut escrow = escrow();
+        let mut first = deposit(key, &mut escrow, Stable::Usdc, 100);
+        let second = deposit(key, &mut escrow, Stable::Usdt, 250);
+        let third = deposit(key, &mut escrow, Stable::Usdc, 50);
+
+        assert_eq!((escrow.deposit_count, escrow.pending_count), (3, 3));
+        assert_eq!((escrow.deposited_usdc, escrow.deposited_usdt), (150, 250));
+
+        settle_deposit(&mut escrow, &mut first, DepositState::Cancelled).unwrap();
+        assert_eq!((escrow.deposit_count, escrow.pending_count), (3, 2));
+        assert_eq!(escrow.deposited_usdc, 50);
+
+        // a settled record can't be settled again
+        assert!(settle_deposit(&mut escrow, &mut first, DepositState::Complete).is_err());
+
+        let pending = sum(&key, &[&second, &third]).unwrap();
+        check_invariants(&escrow, &pending, 50, 250).unwrap();
+    }
+
+    #[test]
+    fn zero_deposits_are_rejected() {
+        let mut escrow = escrow();
+
+        assert_eq!(
+            record_deposit(&mut escrow, &Stable::Usdc, 0).err(),
+            Some(ErrorCode::ZeroDepositAmount.into())
+        );
+        assert_eq!((escrow.deposit_count, escrow.pending_count), (0, 0));
+        assert_eq!((escrow.deposited_usdc, escrow.deposited_usdt), (0, 0));
+    }
+
+    #[test]
+    fn vault_surplus_is_tolerated_but_a_shortfall_is_not() {
+        let key = Pubkey::new_unique();
+        let mut escrow = escrow();
+        let record = deposit(key, &mut escrow, Stable::Usdc, 100);
+        let pending = sum(&key, &[&record]).unwrap();
+
+        check_invariants(&escrow, &pending, 101, 0).unwrap();
+        check_invariants(&escrow, &pending, 100, 7).unwrap();
+        assert!(check_invariants(&escrow, &pending, 99, 0).is_err());
+    }
+
+    #[test]
+    fn only_every_pending_record_once_passes() {
+        let key = Pubkey::new_unique();
+        let mut escrow = escrow();
+        let mut settled = deposit(key, &mut escrow, Stable::Usdc, 100);
+        let first = deposit(key, &mut escrow, Stable::Usdc, 20);
+        let second = deposit(key, &mut escrow, Stable::Usdc, 30);
+        settle_deposit(&mut escrow, &mut settled, DepositState::Complete).unwrap();
+
+        let pending = sum(&key, &[&first, &second]).unwrap();
+        check_invariants(&escrow, &pending, 50, 0).unwrap();
+
+        // a missing record
+        let pending = sum(&key, &[&second]).unwrap();
+        assert!(check_invariants(&escrow, &pending, 50, 0).is_err());
+
+        // settled, duplicated, out of order or foreign records
+        assert!(sum(&key, &[&settled, &first, &second]).is_err());
+        assert!(sum(&key, &[&first, &first]).is_err());
+        assert!(sum(&key, &[&second, &first]).is_err());
+        assert!(sum(&Pubkey::new_unique(), &[&first, &second]).is_err());
+    }
+}
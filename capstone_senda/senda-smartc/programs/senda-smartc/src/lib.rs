@@ -3,8 +3,10 @@ use anchor_lang::prelude::*;
 
 mod instructions;
 use instructions::*;
-mod error;
-mod state;
+pub mod error;
+mod ledger;
+mod migration;
+pub mod state;
 
 declare_id!("B3DT8RTGLr4k34jidDKKDYaLZcsveSmMVD7CWfvq8bgn");
 
@@ -30,6 +32,10 @@ pub mod senda_smartc {
         ctx.accounts.deposit(stable, authorization, &ctx.bumps, amount)
     }
 
+    pub fn accept_deposit(ctx: Context<AcceptDeposit>, recent_blockhash: [u8; 32]) -> Result<()> {
+        ctx.accounts.accept_deposit(recent_blockhash)
+    }
+
     pub fn cancel(ctx: Context<Cancel>, recent_blockhash: [u8; 32]) -> Result<()> {
         ctx.accounts.cancel(recent_blockhash)
     }
@@ -37,4 +43,29 @@ pub mod senda_smartc {
     pub fn release(ctx: Context<Release>, recent_blockhash: [u8; 32]) -> Result<()> {
         ctx.accounts.release(recent_blockhash)
     }
+
+    pub fn net_settle(
+        ctx: Context<NetSettle>,
+        _stable: state::Stable,
+        blockhash_ab: [u8; 32],
+        blockhash_ba: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.net_settle(blockhash_ab, blockhash_ba)
+    }
+
+    pub fn verify_escrow_invariants(ctx: Context<VerifyEscrowInvariants>) -> Result<()> {
+        ctx.accounts.verify(ctx.remaining_accounts)
+    }
+
+    pub fn migrate_factory(ctx: Context<MigrateFactory>) -> Result<()> {
+        ctx.accounts.migrate_factory()
+    }
+
+    pub fn migrate_escrow(ctx: Context<MigrateEscrow>) -> Result<()> {
+        ctx.accounts.migrate_escrow()
+    }
+
+    pub fn migrate_deposit_record(ctx: Context<MigrateDepositRecord>) -> Result<()> {
+        ctx.accounts.migrate_deposit_record()
+    }
 }
\ No newline at end of file
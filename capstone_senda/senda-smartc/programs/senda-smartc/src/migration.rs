@@ -0,0 +1,371 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::Discriminator;
+
+use crate::error::ErrorCode;
+use crate::state::{
+    DepositRecord, DepositState, Escrow, EscrowState, Factory, SignaturePolicy, Stable,
+    DEPOSIT_RECORD_VERSION, ESCROW_VERSION, FACTORY_VERSION,
+};
+
+// Layouts as deployed before accounts carried a version byte. They share the
+// discriminator of their current counterparts and are told apart by data length.
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct FactoryV0 {
+    pub admin: Pubkey,
+    pub factory_bump: u8,
+    pub mint_authority: Pubkey,
+    pub mint_auth_bump: u8,
+    pub escrow_count: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct EscrowV0 {
+    pub seed: u64,
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub authority: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub usdt_mint: Pubkey,
+    pub vault_usdc: Pubkey,
+    pub vault_usdt: Pubkey,
+    pub bump: u8,
+    pub vault_usdc_bump: u8,
+    pub vault_usdt_bump: u8,
+    pub deposited_usdc: u64,
+    pub deposited_usdt: u64,
+    pub deposit_count: u64,
+    pub state: EscrowState,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct DepositRecordV0 {
+    pub escrow: Pubkey,
+    pub deposit_idx: u64,
+    pub amount: u64,
+    pub policy: SignaturePolicy,
+    pub bump: u8,
+    pub stable: Stable,
+    pub state: DepositState,
+}
+
+impl FactoryV0 {
+    pub fn upgrade(self) -> Factory {
+        Factory {
+            version: FACTORY_VERSION,
+            admin: self.admin,
+            factory_bump: self.factory_bump,
+            mint_authority: self.mint_authority,
+            mint_auth_bump: self.mint_auth_bump,
+            escrow_count: self.escrow_count,
+            reserved: [0; 64],
+        }
+    }
+}
+
+impl EscrowV0 {
+    // The deposited totals start empty and are rebuilt by `migrate_deposit_record`
+    // from the escrow's pending records, the source of truth. Legacy totals may
+    // have drifted from them.
+    pub fn upgrade(self) -> Escrow {
+        Escrow {
+            version: ESCROW_VERSION,
+            seed: self.seed,
+            sender: self.sender,
+            receiver: self.receiver,
+            authority: self.authority,
+            usdc_mint: self.usdc_mint,
+            usdt_mint: self.usdt_mint,
+            vault_usdc: self.vault_usdc,
+            vault_usdt: self.vault_usdt,
+            bump: self.bump,
+            vault_usdc_bump: self.vault_usdc_bump,
+            vault_usdt_bump: self.vault_usdt_bump,
+            deposited_usdc: 0,
+            deposited_usdt: 0,
+            deposit_count: self.deposit_count,
+            pending_count: 0,
+            state: self.state,
+            reserved: [0; 120],
+        }
+    }
+}
+
+impl DepositRecordV0 {
+    pub fn upgrade(self) -> DepositRecord {
+        // Legacy deposits were never accepted, so they get the policy a new deposit would
+        let cancel_policy = self.policy.initial_cancel_policy();
+
+        DepositRecord {
+            version: DEPOSIT_RECORD_VERSION,
+            escrow: self.escrow,
+            deposit_idx: self.deposit_idx,
+            amount: self.amount,
+            policy: self.policy,
+            bump: self.bump,
+            stable: self.stable,
+            state: self.state,
+            cancel_policy,
+            reserved: [0; 64],
+        }
+    }
+}
+
+pub fn read_legacy<T, A>(info: &AccountInfo, legacy_space: usize, current_space: usize) -> Result<T>
+where
+    T: AnchorDeserialize,
+    A: Discriminator,
+{
+    require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidAccountLayout);
+
+    let data = info.try_borrow_data()?;
+    require!(data.starts_with(A::DISCRIMINATOR), ErrorCode::InvalidAccountLayout);
+    require!(data.len() != 8 + current_space, ErrorCode::AlreadyMigrated);
+    require!(data.len() == 8 + legacy_space, ErrorCode::InvalidAccountLayout);
+
+    let legacy = T::deserialize(&mut &data[8..])?;
+
+    Ok(legacy)
+}
+
+pub fn write_upgraded<'info, T: AccountSerialize>(
+    info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    upgraded: &T,
+) -> Result<()> {
+    let new_len = 8 + space;
+    let rent = Rent::get()?.minimum_balance(new_len);
+    let shortfall = rent.saturating_sub(info.lamports());
+
+    if shortfall > 0 {
+        let cpi_ctx = CpiContext::new(
+            system_program.to_account_info(),
+            Transfer {
+                from: payer.to_account_info(),
+                to: info.clone(),
+            },
+        );
+        transfer(cpi_ctx, shortfall)?;
+    }
+
+    info.realloc(new_len, true)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    upgraded.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger;
+    use crate::state::CancelPolicy;
+
+    // An account of `space` bytes holding `A`'s discriminator followed by `value`
+    fn account_data<A: Discriminator>(value: &impl AnchorSerialize, space: usize) -> Vec<u8> {
+        let mut data = A::DISCRIMINATOR.to_vec();
+        value.serialize(&mut data).unwrap();
+        data.resize(8 + space, 0);
+        data
+    }
+
+    // Reads `data` as a program-owned account and upgrades it like the migrate
+    // instructions do, then checks the result fills the current layout
+    fn migrate<T, A>(mut data: Vec<u8>, legacy_space: usize, upgrade: impl FnOnce(T) -> A) -> Result<A>
+    where
+        T: AnchorDeserialize,
+        A: Discriminator + AccountSerialize + AccountDeserialize + Space,
+    {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &crate::ID, false, 0);
+        let legacy = read_legacy::<T, A>(&info, legacy_space, A::INIT_SPACE)?;
+
+        let mut upgraded = Vec::new();
+        upgrade(legacy).try_serialize(&mut upgraded)?;
+        upgraded.resize(8 + A::INIT_SPACE, 0);
+
+        // and a second run on the migrated account is turned away
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, true, &mut lamports, &mut upgraded, &crate::ID, false, 0);
+        assert_eq!(
+            read_legacy::<T, A>(&info, legacy_space, A::INIT_SPACE).err(),
+            Some(ErrorCode::AlreadyMigrated.into())
+        );
+
+        A::try_deserialize(&mut &upgraded[..])
+    }
+
+    #[test]
+    fn factory_fields_survive_migration() {
+        let legacy = FactoryV0 {
+            admin: Pubkey::new_unique(),
+            factory_bump: 254,
+            mint_authority: Pubkey::new_unique(),
+            mint_auth_bump: 253,
+            escrow_count: 42,
+        };
+        let data = account_data::<Factory>(&legacy, FactoryV0::INIT_SPACE);
+
+        let factory = migrate(data, FactoryV0::INIT_SPACE, FactoryV0::upgrade).unwrap();
+
+        assert_eq!(factory.version, FACTORY_VERSION);
+        assert_eq!(factory.admin, legacy.admin);
+        assert_eq!(factory.factory_bump, 254);
+        assert_eq!(factory.mint_authority, legacy.mint_authority);
+        assert_eq!(factory.mint_auth_bump, 253);
+        assert_eq!(factory.escrow_count, 42);
+    }
+
+    #[test]
+    fn escrow_fields_survive_migration() {
+        let legacy = EscrowV0 {
+            seed: 7,
+            sender: Pubkey::new_unique(),
+            receiver: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            usdc_mint: Pubkey::new_unique(),
+            usdt_mint: Pubkey::new_unique(),
+            vault_usdc: Pubkey::new_unique(),
+            vault_usdt: Pubkey::new_unique(),
+            bump: 250,
+            vault_usdc_bump: 251,
+            vault_usdt_bump: 252,
+            deposited_usdc: 1_000,
+            deposited_usdt: 2_000,
+            deposit_count: 5,
+            state: EscrowState::Closed,
+        };
+        let data = account_data::<Escrow>(&legacy, EscrowV0::INIT_SPACE);
+
+        let escrow = migrate(data, EscrowV0::INIT_SPACE, EscrowV0::upgrade).unwrap();
+
+        assert_eq!(escrow.version, ESCROW_VERSION);
+        assert_eq!(escrow.seed, 7);
+        assert_eq!(
+            [escrow.sender, escrow.receiver, escrow.authority],
+            [legacy.sender, legacy.receiver, legacy.authority]
+        );
+        assert_eq!(
+            [escrow.usdc_mint, escrow.usdt_mint, escrow.vault_usdc, escrow.vault_usdt],
+            [legacy.usdc_mint, legacy.usdt_mint, legacy.vault_usdc, legacy.vault_usdt]
+        );
+        assert_eq!((escrow.bump, escrow.vault_usdc_bump, escrow.vault_usdt_bump), (250, 251, 252));
+        // rebuilt from the deposit records as they migrate
+        assert_eq!((escrow.deposited_usdc, escrow.deposited_usdt), (0, 0));
+        assert_eq!((escrow.deposit_count, escrow.pending_count), (5, 0));
+        assert!(escrow.state == EscrowState::Closed);
+    }
+
+    fn legacy_escrow(deposited_usdc: u64, deposited_usdt: u64, deposit_count: u64) -> EscrowV0 {
+        EscrowV0 {
+            seed: 0,
+            sender: Pubkey::new_unique(),
+            receiver: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            usdc_mint: Pubkey::new_unique(),
+            usdt_mint: Pubkey::new_unique(),
+            vault_usdc: Pubkey::new_unique(),
+            vault_usdt: Pubkey::new_unique(),
+            bump: 255,
+            vault_usdc_bump: 255,
+            vault_usdt_bump: 255,
+            deposited_usdc,
+            deposited_usdt,
+            deposit_count,
+            state: EscrowState::Active,
+        }
+    }
+
+    fn legacy_record(escrow: Pubkey, deposit_idx: u64, stable: Stable, amount: u64, state: DepositState) -> Vec<u8> {
+        let legacy = DepositRecordV0 {
+            escrow,
+            deposit_idx,
+            amount,
+            policy: SignaturePolicy::Dual,
+            bump: 255,
+            stable,
+            state,
+        };
+        account_data::<DepositRecord>(&legacy, DepositRecordV0::INIT_SPACE)
+    }
+
+    #[test]
+    fn legacy_escrow_totals_are_rebuilt_from_its_pending_records() {
+        let escrow_key = Pubkey::new_unique();
+
+        // totals left off by the old accounting, they don't match the records
+        let data = account_data::<Escrow>(&legacy_escrow(999, 0, 5), EscrowV0::INIT_SPACE);
+        let mut escrow = migrate(data, EscrowV0::INIT_SPACE, EscrowV0::upgrade).unwrap();
+
+        let records = [
+            legacy_record(escrow_key, 0, Stable::Usdc, 100, DepositState::Complete),
+            legacy_record(escrow_key, 1, Stable::Usdc, 200, DepositState::PendingWithdrawal),
+            legacy_record(escrow_key, 2, Stable::Usdt, 300, DepositState::PendingWithdrawal),
+            legacy_record(escrow_key, 3, Stable::Usdt, 400, DepositState::Cancelled),
+            legacy_record(escrow_key, 4, Stable::Usdc, 500, DepositState::PendingWithdrawal),
+        ];
+
+        // each record in a transaction of its own, adding itself to the escrow
+        let mut pending = ledger::PendingTotals::default();
+        for data in records {
+            let record = migrate(data, DepositRecordV0::INIT_SPACE, DepositRecordV0::upgrade).unwrap();
+            ledger::adopt_pending(&mut escrow, &record).unwrap();
+            if record.state == DepositState::PendingWithdrawal {
+                pending.add(&record).unwrap();
+            }
+        }
+
+        assert_eq!((escrow.deposited_usdc, escrow.deposited_usdt), (700, 300));
+        assert_eq!((escrow.deposit_count, escrow.pending_count), (5, 3));
+        ledger::check_invariants(&escrow, &pending, 999, 300).unwrap();
+    }
+
+    #[test]
+    fn deposit_record_fields_survive_migration() {
+        let signer = Pubkey::new_unique();
+        let legacy = DepositRecordV0 {
+            escrow: Pubkey::new_unique(),
+            deposit_idx: 3,
+            amount: 500,
+            policy: SignaturePolicy::Single { signer },
+            bump: 249,
+            stable: Stable::Usdt,
+            state: DepositState::PendingWithdrawal,
+        };
+        let data = account_data::<DepositRecord>(&legacy, DepositRecordV0::INIT_SPACE);
+
+        let record = migrate(data, DepositRecordV0::INIT_SPACE, DepositRecordV0::upgrade).unwrap();
+
+        assert_eq!(record.version, DEPOSIT_RECORD_VERSION);
+        assert_eq!(record.escrow, legacy.escrow);
+        assert_eq!((record.deposit_idx, record.amount, record.bump), (3, 500, 249));
+        assert!(record.policy == SignaturePolicy::Single { signer });
+        assert!(record.stable == Stable::Usdt);
+        assert!(record.state == DepositState::PendingWithdrawal);
+        assert!(record.cancel_policy == CancelPolicy::SenderOnly);
+    }
+
+    #[test]
+    fn unknown_layouts_are_rejected() {
+        let legacy = FactoryV0 {
+            admin: Pubkey::new_unique(),
+            factory_bump: 254,
+            mint_authority: Pubkey::new_unique(),
+            mint_auth_bump: 253,
+            escrow_count: 1,
+        };
+
+        // a truncated account
+        let data = account_data::<Factory>(&legacy, FactoryV0::INIT_SPACE - 1);
+        assert!(migrate(data, FactoryV0::INIT_SPACE, FactoryV0::upgrade).is_err());
+
+        // another account type's discriminator
+        let data = account_data::<Escrow>(&legacy, FactoryV0::INIT_SPACE);
+        assert!(migrate(data, FactoryV0::INIT_SPACE, FactoryV0::upgrade).is_err());
+    }
+}
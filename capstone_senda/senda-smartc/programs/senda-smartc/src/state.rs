@@ -7,6 +7,15 @@ pub enum Stable {
     Usdt,
 }
 
+impl Stable {
+    pub fn vault_seed(&self) -> &'static [u8] {
+        match self {
+            Stable::Usdc => b"usdc-vault",
+            Stable::Usdt => b"usdt-vault",
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
 #[derive(InitSpace)]
 pub enum EscrowState {
@@ -26,16 +35,19 @@ pub enum DepositState {
 #[account]
 #[derive(InitSpace)]
 pub struct Factory {
+    pub version:      u8,
     pub admin:        Pubkey,
     pub factory_bump: u8,
     pub mint_authority:   Pubkey,
     pub mint_auth_bump:   u8,
     pub escrow_count: u64,
+    pub reserved:     [u8; 64],
 }
 
 #[account]
 #[derive(InitSpace)]
 pub struct Escrow {
+    pub version: u8,
     pub seed: u64,
 
     pub sender: Pubkey,
@@ -55,13 +67,27 @@ pub struct Escrow {
     pub deposited_usdc: u64,
     pub deposited_usdt: u64,
     pub deposit_count: u64,
+    // deposits still PendingWithdrawal, the records `verify_escrow_invariants` expects
+    pub pending_count: u64,
 
     pub state: EscrowState,
+
+    pub reserved: [u8; 120],
+}
+
+impl Escrow {
+    pub fn mint(&self, stable: &Stable) -> Pubkey {
+        match stable {
+            Stable::Usdc => self.usdc_mint,
+            Stable::Usdt => self.usdt_mint,
+        }
+    }
 }
 
 #[account]
 #[derive(InitSpace)]
 pub struct DepositRecord {
+    pub version: u8,
     pub escrow: Pubkey,
     pub deposit_idx: u64,
     pub amount: u64,
@@ -69,6 +95,8 @@ pub struct DepositRecord {
     pub bump: u8,
     pub stable: Stable,
     pub state: DepositState,
+    pub cancel_policy: CancelPolicy,
+    pub reserved: [u8; 64],
 }
 
 #[derive(AnchorDeserialize, AnchorSerialize, Clone, PartialEq)]
@@ -80,6 +108,35 @@ pub enum SignaturePolicy {
     },
 }
 
+impl SignaturePolicy {
+    // Dual deposits already need both parties to move funds, so they start out mutual
+    pub fn initial_cancel_policy(&self) -> CancelPolicy {
+        match self {
+            SignaturePolicy::Dual => CancelPolicy::Mutual,
+            SignaturePolicy::Single { .. } => CancelPolicy::SenderOnly,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+#[derive(InitSpace)]
+pub enum CancelPolicy {
+    // Not yet accepted by the counterparty, the depositor may reclaim alone
+    SenderOnly,
+    // Accepted, the depositor needs the counterparty to agree
+    Mutual,
+}
+
+impl CancelPolicy {
+    // The counterparty can always decline a deposit, which refunds the depositor
+    pub fn allows(self, depositor_signed: bool, counterparty_signed: bool) -> bool {
+        match self {
+            CancelPolicy::SenderOnly => depositor_signed || counterparty_signed,
+            CancelPolicy::Mutual => counterparty_signed,
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
 #[derive(InitSpace)]
 pub enum AuthorizedBy {
@@ -89,7 +146,7 @@ pub enum AuthorizedBy {
 }
 
 impl AuthorizedBy {
-    pub fn to_policy(&self, sender: Pubkey, receiver: Pubkey) -> SignaturePolicy {
+    pub fn to_policy(self, sender: Pubkey, receiver: Pubkey) -> SignaturePolicy {
         match self {
             AuthorizedBy::Sender => SignaturePolicy::Single { signer: sender },
             AuthorizedBy::Receiver => SignaturePolicy::Single { signer: receiver },
@@ -98,5 +155,10 @@ impl AuthorizedBy {
     }
 }
 
+// Bump when a layout changes and teach `migration` how to upgrade the previous one
+pub const FACTORY_VERSION: u8 = 1;
+pub const ESCROW_VERSION: u8 = 1;
+pub const DEPOSIT_RECORD_VERSION: u8 = 1;
+
 pub const USDC_MINT_ADDR: &str = "EPjFWdd5AufqSSqeM2qctBxi8LoRBdQkj6mjjFG2Afa";
 pub const USDT_MINT_ADDR: &str = "Es9vMFrzaCERnAawET5VsmZ6T4dQW5Ad9asmaaAEA7ZT";
@@ -69,6 +69,22 @@ type CancelAccounts = Parameters<
   ReturnType<typeof program.methods.cancel>["accounts"]
 >[0];
 
+type AcceptDepositAccounts = Parameters<
+  ReturnType<typeof program.methods.acceptDeposit>["accounts"]
+>[0];
+
+type MigrateEscrowAccounts = Parameters<
+  ReturnType<typeof program.methods.migrateEscrow>["accounts"]
+>[0];
+
+type NetSettleAccounts = Parameters<
+  ReturnType<typeof program.methods.netSettle>["accounts"]
+>[0];
+
+type VerifyEscrowInvariantsAccounts = Parameters<
+  ReturnType<typeof program.methods.verifyEscrowInvariants>["accounts"]
+>[0];
+
 const confirm = async (signature: string): Promise<string> => {
   const block = await connection.getLatestBlockhash();
   await connection.confirmTransaction({ signature, ...block });
@@ -92,6 +108,23 @@ const getTokenBalance = async (tokenAccount: PublicKey): Promise<number> => {
   }
 };
 
+// Resolves when the transaction fails with `code` somewhere in the error or its logs
+const expectError = async (tx: Promise<unknown>, code: string): Promise<void> => {
+  try {
+    await tx;
+  } catch (err) {
+    const details = `${err}\n${(err.logs ?? []).join("\n")}`;
+    assert.ok(details.includes(code), `Expected ${code}, got: ${details}`);
+    return;
+  }
+  assert.fail(`Expected the transaction to fail with ${code}`);
+};
+
+const getRawTokenBalance = async (tokenAccount: PublicKey): Promise<BN> => {
+  const info = await connection.getTokenAccountBalance(tokenAccount);
+  return new BN(info.value.amount);
+};
+
 const getRecentBlockhashArray = async (connection: Connection): Promise<number[]> => {
   const { blockhash } = await connection.getLatestBlockhash();
   // Convert the blockhash to a PublicKey first, then get its bytes
@@ -247,6 +280,203 @@ describe("senda_dapp", () => {
     programId
   );
 
+  // Fresh parties with their own escrows, used by the ledger, cancel policy and
+  // netting cases at the end of this suite
+
+  type Party = { keypair: Keypair; usdcAta: PublicKey; usdtAta: PublicKey };
+  type OpenEscrow = { escrowPda: PublicKey; vaultUsdc: PublicKey; vaultUsdt: PublicKey };
+  type OpenDeposit = { blockhashArray: number[]; depositRecordPda: PublicKey };
+
+  // Each party gets SOL, both token accounts and 1 USDT
+  const fundParties = async (count: number): Promise<Party[]> => {
+    const parties = Array.from({ length: count }, () => {
+      const keypair = Keypair.generate();
+      return {
+        keypair,
+        usdcAta: getAssociatedTokenAddressSync(USDC_MINT_ADDR, keypair.publicKey, false),
+        usdtAta: getAssociatedTokenAddressSync(USDT_MINT_ADDR, keypair.publicKey, false),
+      };
+    });
+    const walletUsdtAta = getAssociatedTokenAddressSync(USDT_MINT_ADDR, authority.publicKey, false);
+
+    const tx = new Transaction();
+    for (const party of parties) {
+      tx.add(
+        SystemProgram.transfer({
+          fromPubkey: authority.publicKey,
+          toPubkey: party.keypair.publicKey,
+          lamports: 0.1 * LAMPORTS_PER_SOL,
+        })
+      )
+        .add(createAssociatedTokenAccountIdempotentInstruction(
+          authority.publicKey,
+          party.usdcAta,
+          party.keypair.publicKey,
+          USDC_MINT_ADDR
+        ))
+        .add(createAssociatedTokenAccountIdempotentInstruction(
+          authority.publicKey,
+          party.usdtAta,
+          party.keypair.publicKey,
+          USDT_MINT_ADDR
+        ))
+        .add(createTransferCheckedInstruction(
+          walletUsdtAta,
+          USDT_MINT_ADDR,
+          party.usdtAta,
+          authority.publicKey,
+          1_000_000_000,
+          usdtDecimals
+        ));
+    }
+    await provider.sendAndConfirm(tx, [authority]);
+
+    return parties;
+  };
+
+  const openEscrow = async (escrowSender: Party, escrowReceiver: Party): Promise<OpenEscrow> => {
+    const [escrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), escrowSender.keypair.publicKey.toBuffer(), escrowReceiver.keypair.publicKey.toBuffer()],
+      program.programId
+    );
+    const [vaultUsdc] = PublicKey.findProgramAddressSync(
+      [Buffer.from("usdc-vault"), escrowPda.toBuffer(), USDC_MINT_ADDR.toBuffer()],
+      program.programId
+    );
+    const [vaultUsdt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("usdt-vault"), escrowPda.toBuffer(), USDT_MINT_ADDR.toBuffer()],
+      program.programId
+    );
+
+    const ix = await program.methods
+      .initializeEscrow(new BN(0))
+      .accounts({
+        escrow: escrowPda,
+        feePayer: authority.publicKey,
+        sender: escrowSender.keypair.publicKey,
+        receiver: escrowReceiver.keypair.publicKey,
+        authority: authority.publicKey,
+        senderUsdcAta: escrowSender.usdcAta,
+        senderUsdtAta: escrowSender.usdtAta,
+        receiverUsdcAta: escrowReceiver.usdcAta,
+        receiverUsdtAta: escrowReceiver.usdtAta,
+        usdcMint: USDC_MINT_ADDR,
+        usdtMint: USDT_MINT_ADDR,
+        vaultUsdc,
+        vaultUsdt,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      } as InitEscrowAccounts)
+      .instruction();
+
+    await web3.sendAndConfirmTransaction(connection, new Transaction().add(ix), [escrowSender.keypair, authority]);
+
+    return { escrowPda, vaultUsdc, vaultUsdt };
+  };
+
+  // Deposit records are seeded by a blockhash, back to back deposits wait for a new one
+  let lastDepositBlockhash = "";
+
+  // `depositor` pays `amount` of `stable` into the escrow for `counterparty`
+  const makeDeposit = async (
+    escrow: OpenEscrow,
+    depositor: Party,
+    counterparty: Party,
+    authorization: object,
+    amount: number,
+    stable: object = { usdt: {} }
+  ): Promise<OpenDeposit> => {
+    let blockhashArray = await getRecentBlockhashArray(connection);
+    while (blockhashArray.join() === lastDepositBlockhash) {
+      await new Promise(resolve => setTimeout(resolve, 500));
+      blockhashArray = await getRecentBlockhashArray(connection);
+    }
+    lastDepositBlockhash = blockhashArray.join();
+    const [depositRecordPda] = getDepositRecordPDA(escrow.escrowPda, depositor.keypair.publicKey, blockhashArray);
+
+    const ix = await program.methods
+      .deposit(stable as any, authorization as any, blockhashArray, new BN(amount))
+      .accounts({
+        escrow: escrow.escrowPda,
+        sender: depositor.keypair.publicKey,
+        receiver: counterparty.keypair.publicKey,
+        authority: authority.publicKey,
+        senderUsdcAta: depositor.usdcAta,
+        senderUsdtAta: depositor.usdtAta,
+        receiverUsdcAta: counterparty.usdcAta,
+        receiverUsdtAta: counterparty.usdtAta,
+        usdcMint: USDC_MINT_ADDR,
+        usdtMint: USDT_MINT_ADDR,
+        vaultUsdc: escrow.vaultUsdc,
+        vaultUsdt: escrow.vaultUsdt,
+        depositRecord: depositRecordPda,
+        feePayer: authority.publicKey,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY
+      } as DepositAccounts)
+      .instruction();
+
+    await web3.sendAndConfirmTransaction(connection, new Transaction().add(ix), [depositor.keypair, authority]);
+
+    return { blockhashArray, depositRecordPda };
+  };
+
+  // Cancels a deposit signed by `signer`, the fee payer, and the authority
+  const cancelDeposit = async (
+    escrow: OpenEscrow,
+    depositor: Party,
+    counterparty: Party,
+    deposit: OpenDeposit,
+    signer: Keypair
+  ): Promise<string> => {
+    const ix = await program.methods
+      .cancel(deposit.blockhashArray)
+      .accounts({
+        escrow: escrow.escrowPda,
+        sender: depositor.keypair.publicKey,
+        receiver: counterparty.keypair.publicKey,
+        authority: authority.publicKey,
+        senderUsdcAta: depositor.usdcAta,
+        senderUsdtAta: depositor.usdtAta,
+        usdcMint: USDC_MINT_ADDR,
+        usdtMint: USDT_MINT_ADDR,
+        vaultUsdc: escrow.vaultUsdc,
+        vaultUsdt: escrow.vaultUsdt,
+        depositRecord: deposit.depositRecordPda,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY
+      } as CancelAccounts)
+      .instruction();
+
+    return web3.sendAndConfirmTransaction(connection, new Transaction().add(ix), [signer, authority]);
+  };
+
+  const acceptDeposit = async (
+    escrow: OpenEscrow,
+    depositor: Party,
+    accepter: Party,
+    deposit: OpenDeposit
+  ): Promise<string> => {
+    const ix = await program.methods
+      .acceptDeposit(deposit.blockhashArray)
+      .accounts({
+        escrow: escrow.escrowPda,
+        sender: depositor.keypair.publicKey,
+        receiver: accepter.keypair.publicKey,
+        authority: authority.publicKey,
+        depositRecord: deposit.depositRecordPda,
+      } as AcceptDepositAccounts)
+      .instruction();
+
+    return web3.sendAndConfirmTransaction(connection, new Transaction().add(ix), [accepter.keypair, authority]);
+  };
+
   it("init_factory succeeds only once", async () => {
     const factoryPda = factoryPDA;
 
@@ -1953,7 +2183,7 @@ describe("senda_dapp", () => {
     }
   });
 
-  it("ensures only depositor can cancel their own deposit", async () => {
+  it("rejects cancellation by someone outside the escrow", async () => {
     
     const depositSender = Keypair.generate();
     const depositReceiver = Keypair.generate();
@@ -2148,7 +2378,7 @@ describe("senda_dapp", () => {
 
     console.log(`Deposit successful for unauthorized release test`);
 
-    const vaultBalanceBefore = await getTokenBalance(vaultUsdc);
+    const vaultBalanceBefore = await getTokenBalance(vaultUsdt);
 
     const cancelIx = await program.methods
       .cancel(blockhashArray)
@@ -2183,42 +2413,8 @@ describe("senda_dapp", () => {
     }
 
     // Verify vault balance is unchanged
-    const vaultBalanceAfter = await getTokenBalance(vaultUsdc);
-    assert.equal(vaultBalanceAfter, vaultBalanceBefore, "Vault should still have 1 USDT after failed cancel attempt");
-
-    const cancelIx2 = await program.methods
-      .cancel(blockhashArray)
-      .accounts({
-        escrow: escrowPda,
-        sender: depositSender.publicKey,
-        receiver: depositReceiver.publicKey,
-        senderUsdcAta: senderUsdcAta,
-        senderUsdtAta: senderUsdtAta,
-        usdcMint: USDC_MINT_ADDR,
-        usdtMint: USDT_MINT_ADDR,
-        vaultUsdc: vaultUsdc,
-        vaultUsdt: vaultUsdt,
-        depositRecord: depositRecordPda,
-        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        systemProgram: SystemProgram.programId,
-        rent: SYSVAR_RENT_PUBKEY
-      } as CancelAccounts)
-      .instruction();
-
-    const cancelTx2 = new Transaction().add(cancelIx2);
-
-    try {
-      await web3.sendAndConfirmTransaction(connection, cancelTx2, [depositReceiver, authority]);
-      assert.fail("Non-depositor should not be able to cancel deposit");
-    } catch (err) {
-      console.log(`Expected error for non-depositor cancel attempt: ${err}`);
-      // Expected error - the non-depositor can't cancel
-    }
-
-    // Verify vault balance is unchanged
-    const vaultBalance2After = await getTokenBalance(vaultUsdc);
-    assert.equal(vaultBalance2After, vaultBalanceBefore, "Vault should still have 1 USDT after failed cancel attempt");
+    const vaultBalanceAfter = await getTokenBalance(vaultUsdt);
+    assert.equal(vaultBalanceAfter, vaultBalanceBefore, "Vault should still hold the deposit after failed cancel attempt");
 
     // Now have the valid depositor cancel
     try {
@@ -2230,7 +2426,7 @@ describe("senda_dapp", () => {
     }
 
     // Verify vault is now empty
-    const vaultBalanceAfterValidCancel = await getTokenBalance(vaultUsdc);
+    const vaultBalanceAfterValidCancel = await getTokenBalance(vaultUsdt);
     assert.equal(vaultBalanceAfterValidCancel, 0, "Vault should be empty after valid depositor cancels");
   });
 
@@ -3226,4 +3422,252 @@ describe("senda_dapp", () => {
       assert.ok(err.toString().includes("custom program error") || err.toString().includes("InvalidState"), "Should fail with InvalidState error");
     }
   });
+
+  it("verifies escrow invariants against pending deposit records only", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrow = await openEscrow(partyA, partyB);
+
+    const settled = await makeDeposit(escrow, partyA, partyB, { sender: {} }, 100_000_000);
+    const first = await makeDeposit(escrow, partyA, partyB, { sender: {} }, 200_000_000);
+    const second = await makeDeposit(escrow, partyA, partyB, { sender: {} }, 300_000_000);
+    await cancelDeposit(escrow, partyA, partyB, settled, partyA.keypair);
+
+    const escrowAccount = await program.account.escrow.fetch(escrow.escrowPda);
+    assert.strictEqual(escrowAccount.depositCount.toNumber(), 3, "Every deposit is counted");
+    assert.strictEqual(escrowAccount.pendingCount.toNumber(), 2, "Only unsettled deposits are pending");
+    assert.strictEqual(escrowAccount.depositedUsdt.toNumber(), 500_000_000, "Deposited total covers pending deposits");
+
+    // Tokens sent straight to the vault are surplus and must not break the check
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        createTransferCheckedInstruction(
+          partyA.usdtAta,
+          USDT_MINT_ADDR,
+          escrow.vaultUsdt,
+          partyA.keypair.publicKey,
+          1,
+          usdtDecimals
+        )
+      ),
+      [partyA.keypair]
+    );
+
+    const verify = (records: PublicKey[]) =>
+      program.methods
+        .verifyEscrowInvariants()
+        .accounts({
+          escrow: escrow.escrowPda,
+          vaultUsdc: escrow.vaultUsdc,
+          vaultUsdt: escrow.vaultUsdt,
+        } as VerifyEscrowInvariantsAccounts)
+        .remainingAccounts(records.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })))
+        .rpc();
+
+    await verify([first.depositRecordPda, second.depositRecordPda]);
+
+    await expectError(verify([second.depositRecordPda]), "IncompleteDepositRecords");
+    await expectError(verify([first.depositRecordPda, first.depositRecordPda]), "IncompleteDepositRecords");
+    await expectError(verify([second.depositRecordPda, first.depositRecordPda]), "IncompleteDepositRecords");
+    await expectError(
+      verify([settled.depositRecordPda, first.depositRecordPda, second.depositRecordPda]),
+      "InvalidState"
+    );
+  });
+
+  it("rejects a zero amount deposit", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrow = await openEscrow(partyA, partyB);
+
+    await expectError(makeDeposit(escrow, partyA, partyB, { sender: {} }, 0), "ZeroDepositAmount");
+
+    const escrowAccount = await program.account.escrow.fetch(escrow.escrowPda);
+    assert.ok(escrowAccount.depositCount.isZero(), "No deposit should be recorded");
+    assert.ok(escrowAccount.pendingCount.isZero(), "No deposit should be pending");
+  });
+
+  it("lets the receiver decline a deposit, refunding the depositor", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrow = await openEscrow(partyA, partyB);
+    const deposit = await makeDeposit(escrow, partyA, partyB, { sender: {} }, 400_000_000);
+
+    const senderBalanceBefore = await getRawTokenBalance(partyA.usdtAta);
+    await cancelDeposit(escrow, partyA, partyB, deposit, partyB.keypair);
+
+    const record = await program.account.depositRecord.fetch(deposit.depositRecordPda);
+    assert.ok(record.state.cancelled, "Declined deposit should be cancelled");
+    assert.equal(await getTokenBalance(escrow.vaultUsdt), 0, "Vault should be empty after the decline");
+    assert.ok(
+      (await getRawTokenBalance(partyA.usdtAta)).eq(senderBalanceBefore.addn(400_000_000)),
+      "Depositor should get the deposit back"
+    );
+  });
+
+  it("stops the depositor cancelling alone once the deposit is accepted", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrow = await openEscrow(partyA, partyB);
+    const deposit = await makeDeposit(escrow, partyA, partyB, { sender: {} }, 400_000_000);
+
+    await acceptDeposit(escrow, partyA, partyB, deposit);
+
+    const record = await program.account.depositRecord.fetch(deposit.depositRecordPda);
+    assert.ok(record.cancelPolicy.mutual, "Accepted deposit should need both parties to cancel");
+
+    await expectError(acceptDeposit(escrow, partyA, partyB, deposit), "DepositAlreadyAccepted");
+    await expectError(cancelDeposit(escrow, partyA, partyB, deposit, partyA.keypair), "InvalidSigner");
+    assert.equal(await getTokenBalance(escrow.vaultUsdt), 0.4, "Vault should still hold the deposit");
+
+    // The receiver agreeing is what lets it go back
+    await cancelDeposit(escrow, partyA, partyB, deposit, partyB.keypair);
+    assert.equal(await getTokenBalance(escrow.vaultUsdt), 0, "Vault should be empty after the mutual cancel");
+  });
+
+  it("keeps a dual signature deposit from being reclaimed by the sender alone", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrow = await openEscrow(partyA, partyB);
+    const deposit = await makeDeposit(escrow, partyA, partyB, { both: {} }, 400_000_000);
+
+    const record = await program.account.depositRecord.fetch(deposit.depositRecordPda);
+    assert.ok(record.cancelPolicy.mutual, "Dual deposits should start out mutual");
+
+    await expectError(cancelDeposit(escrow, partyA, partyB, deposit, partyA.keypair), "InvalidSigner");
+    await expectError(acceptDeposit(escrow, partyA, partyB, deposit), "DepositAlreadyAccepted");
+    assert.equal(await getTokenBalance(escrow.vaultUsdt), 0.4, "Vault should still hold the deposit");
+  });
+
+  it("rejects accept_deposit from someone outside the escrow", async () => {
+    const [partyA, partyB, outsider] = await fundParties(3);
+    const escrow = await openEscrow(partyA, partyB);
+    const deposit = await makeDeposit(escrow, partyA, partyB, { sender: {} }, 400_000_000);
+
+    await expectError(acceptDeposit(escrow, partyA, outsider, deposit), "InvalidParties");
+
+    const record = await program.account.depositRecord.fetch(deposit.depositRecordPda);
+    assert.ok(record.cancelPolicy.senderOnly, "Deposit should still be cancellable by the depositor");
+  });
+
+  // Layout upgrades themselves are covered by the unit tests in migration.rs
+  it("rejects migrating an escrow already on the current layout", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrow = await openEscrow(partyA, partyB);
+
+    await expectError(
+      program.methods
+        .migrateEscrow()
+        .accounts({
+          payer: authority.publicKey,
+          escrow: escrow.escrowPda,
+          systemProgram: SystemProgram.programId,
+        } as MigrateEscrowAccounts)
+        .rpc(),
+      "AlreadyMigrated"
+    );
+  });
+
+  // A pays B through escrowAb, B pays A through escrowBa, both in USDT
+  const netSettle = async (
+    partyA: Party,
+    partyB: Party,
+    escrowAb: OpenEscrow,
+    escrowBa: OpenEscrow,
+    depositAb: OpenDeposit,
+    depositBa: OpenDeposit
+  ): Promise<string> => {
+    const ix = await program.methods
+      .netSettle({ usdt: {} }, depositAb.blockhashArray, depositBa.blockhashArray)
+      .accounts({
+        escrowAb: escrowAb.escrowPda,
+        escrowBa: escrowBa.escrowPda,
+        partyA: partyA.keypair.publicKey,
+        partyB: partyB.keypair.publicKey,
+        authority: authority.publicKey,
+        mint: USDT_MINT_ADDR,
+        vaultAb: escrowAb.vaultUsdt,
+        vaultBa: escrowBa.vaultUsdt,
+        partyAAta: partyA.usdtAta,
+        partyBAta: partyB.usdtAta,
+        depositAb: depositAb.depositRecordPda,
+        depositBa: depositBa.depositRecordPda,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as NetSettleAccounts)
+      .instruction();
+
+    return web3.sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(ix),
+      [partyA.keypair, partyB.keypair, authority]
+    );
+  };
+
+  it("net settles opposite deposits of unequal amounts", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrowAb = await openEscrow(partyA, partyB);
+    const escrowBa = await openEscrow(partyB, partyA);
+    const depositAb = await makeDeposit(escrowAb, partyA, partyB, { sender: {} }, 300_000_000);
+    const depositBa = await makeDeposit(escrowBa, partyB, partyA, { sender: {} }, 100_000_000);
+
+    await netSettle(partyA, partyB, escrowAb, escrowBa, depositAb, depositBa);
+
+    // Only the 0.2 USDT difference changes hands
+    assert.ok((await getRawTokenBalance(partyA.usdtAta)).eqn(800_000_000), "A should be down the difference");
+    assert.ok((await getRawTokenBalance(partyB.usdtAta)).eqn(1_200_000_000), "B should be up the difference");
+    assert.ok((await getRawTokenBalance(escrowAb.vaultUsdt)).isZero(), "A to B vault should be empty");
+    assert.ok((await getRawTokenBalance(escrowBa.vaultUsdt)).isZero(), "B to A vault should be empty");
+
+    for (const [escrow, deposit] of [[escrowAb, depositAb], [escrowBa, depositBa]] as const) {
+      const record = await program.account.depositRecord.fetch(deposit.depositRecordPda);
+      assert.ok(record.state.complete, "Netted deposits should be complete");
+
+      const escrowAccount = await program.account.escrow.fetch(escrow.escrowPda);
+      assert.ok(escrowAccount.depositedUsdt.isZero(), "Netted deposits should leave the ledger");
+      assert.ok(escrowAccount.pendingCount.isZero(), "Netted deposits should no longer be pending");
+    }
+  });
+
+  it("net settles opposite deposits of equal amounts", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrowAb = await openEscrow(partyA, partyB);
+    const escrowBa = await openEscrow(partyB, partyA);
+    const depositAb = await makeDeposit(escrowAb, partyA, partyB, { sender: {} }, 250_000_000);
+    const depositBa = await makeDeposit(escrowBa, partyB, partyA, { sender: {} }, 250_000_000);
+
+    await netSettle(partyA, partyB, escrowAb, escrowBa, depositAb, depositBa);
+
+    // The deposits cancel out, everyone ends where they started
+    assert.ok((await getRawTokenBalance(partyA.usdtAta)).eqn(1_000_000_000), "A should be made whole");
+    assert.ok((await getRawTokenBalance(partyB.usdtAta)).eqn(1_000_000_000), "B should be made whole");
+    assert.ok((await getRawTokenBalance(escrowAb.vaultUsdt)).isZero(), "A to B vault should be empty");
+    assert.ok((await getRawTokenBalance(escrowBa.vaultUsdt)).isZero(), "B to A vault should be empty");
+  });
+
+  it("refuses to net deposits of different stables", async () => {
+    const [partyA, partyB] = await fundParties(2);
+    const escrowAb = await openEscrow(partyA, partyB);
+    const escrowBa = await openEscrow(partyB, partyA);
+
+    const walletUsdcAta = getAssociatedTokenAddressSync(USDC_MINT_ADDR, authority.publicKey, false);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        createTransferCheckedInstruction(
+          walletUsdcAta,
+          USDC_MINT_ADDR,
+          partyB.usdcAta,
+          authority.publicKey,
+          100_000,
+          usdcDecimals
+        )
+      ),
+      [authority]
+    );
+
+    const depositAb = await makeDeposit(escrowAb, partyA, partyB, { sender: {} }, 100_000_000);
+    const depositBa = await makeDeposit(escrowBa, partyB, partyA, { sender: {} }, 100_000, { usdc: {} });
+
+    await expectError(netSettle(partyA, partyB, escrowAb, escrowBa, depositAb, depositBa), "NettingMismatch");
+
+    const record = await program.account.depositRecord.fetch(depositAb.depositRecordPda);
+    assert.ok(record.state.pendingWithdrawal, "Deposits should stay pending");
+    assert.ok((await getRawTokenBalance(escrowAb.vaultUsdt)).eqn(100_000_000), "Vault should be untouched");
+  });
 });
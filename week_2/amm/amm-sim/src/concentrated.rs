@@ -0,0 +1,477 @@
+use core::ops::RangeInclusive;
+
+use crate::{mul_div, mul_wide, protocol_fee, to_u64, MathError, Result, SwapResult, XYAmounts, BPS};
+
+// Concentrated liquidity. Positions add liquidity over a range of ticks
+// [lower, upper), tick i sitting at price 1.0001^i (y per x). The pool keeps the
+// square root of the price as Q64.64 and the liquidity active at that price, and
+// a swap moves the price one initialized tick at a time, picking up or dropping
+// the liquidity of the ranges it enters and leaves.
+//
+// Fee growth is Q64.64 fees per unit of liquidity and wraps on purpose: only the
+// difference between two readings is ever used.
+
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+
+const Q64: u128 = 1 << 64;
+
+// floor(sqrt(1.0001) · 2^64)
+const SQRT_BASE_X64: u128 = 18_447_666_387_855_959_850;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolState {
+    pub sqrt_price: u128,
+    // Greatest tick at or below the current price
+    pub tick: i32,
+    // Liquidity of the positions whose range holds the current price
+    pub liquidity: u128,
+    pub fee_growth_global_x: u128,
+    pub fee_growth_global_y: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick {
+    pub index: i32,
+    // Liquidity picked up when the price crosses the tick upwards
+    pub liquidity_net: i128,
+    // Liquidity of every position bounded by the tick, the tick is dropped at zero
+    pub liquidity_gross: u128,
+    // Fee growth on the side of the tick away from the current price
+    pub fee_growth_outside_x: u128,
+    pub fee_growth_outside_y: u128,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub liquidity: u128,
+    // Fee growth inside the range when the position was last touched
+    pub fee_growth_inside_x: u128,
+    pub fee_growth_inside_y: u128,
+    pub tokens_owed_x: u64,
+    pub tokens_owed_y: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcentratedSwap {
+    pub swap: SwapResult,
+    // Part of swap.fee owed to the protocol, the rest went to fee growth
+    pub protocol_fee: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SwapStep {
+    sqrt_price: u128,
+    amount_in: u128,
+    amount_out: u128,
+    fee: u128,
+}
+
+impl PoolState {
+    pub fn new(sqrt_price: u128) -> Result<Self> {
+        Ok(Self {
+            sqrt_price,
+            tick: tick_at_sqrt_price(sqrt_price)?,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+        })
+    }
+
+    // Swaps amount_in of x (is_x) or y against the ticks, sorted by index, which
+    // have to be every initialized tick in `range`. The whole input has to fill:
+    // running out of liquidity, or leaving the range, on the way is an error.
+    pub fn swap(
+        &mut self,
+        ticks: &mut [Tick],
+        range: RangeInclusive<i32>,
+        is_x: bool,
+        amount_in: u64,
+        fee: u16,
+        protocol_fee_share: u16,
+    ) -> Result<ConcentratedSwap> {
+        if amount_in == 0 {
+            return Err(MathError::InvalidAmount);
+        }
+
+        let mut remaining = amount_in as u128;
+        let (mut withdraw, mut fee_total, mut protocol_total) = (0u128, 0u128, 0u64);
+
+        let (range_start, range_end) = (*range.start(), *range.end());
+
+        while remaining != 0 {
+            // every tick between the price and the next target has to be known,
+            // moving up the tick at the price itself is never crossed
+            let known_from = match is_x && range_start > MIN_TICK {
+                true => range_start,
+                false => range_start - 1,
+            };
+            if self.tick < known_from || self.tick > range_end {
+                return Err(MathError::TickRangeExceeded);
+            }
+
+            let next = match is_x {
+                true => ticks.iter().rposition(|tick| tick.index <= self.tick),
+                false => ticks.iter().position(|tick| tick.index > self.tick),
+            };
+            // without an initialized tick ahead the price can run to the edge of the range
+            let target_tick = match (next, is_x) {
+                (Some(i), _) => ticks[i].index,
+                (None, true) => range_start.max(MIN_TICK),
+                (None, false) => range_end.saturating_add(1).min(MAX_TICK),
+            };
+            let sqrt_target = sqrt_price_at_tick(target_tick)?;
+
+            if next.is_none() && sqrt_target == self.sqrt_price {
+                return match target_tick {
+                    MIN_TICK | MAX_TICK => Err(MathError::InsufficientLiquidity),
+                    _ => Err(MathError::TickRangeExceeded),
+                };
+            }
+
+            let step = swap_step(self.sqrt_price, sqrt_target, self.liquidity, remaining, fee, is_x)?;
+            remaining -= step.amount_in + step.fee;
+            withdraw = withdraw.checked_add(step.amount_out).ok_or(MathError::Overflow)?;
+            fee_total += step.fee;
+
+            let step_protocol_fee = protocol_fee(to_u64(step.fee)?, protocol_fee_share)?;
+            protocol_total = protocol_total.checked_add(step_protocol_fee).ok_or(MathError::Overflow)?;
+
+            if self.liquidity != 0 {
+                let growth = mul_div(step.fee - step_protocol_fee as u128, Q64, self.liquidity, false)?;
+                let global = match is_x {
+                    true => &mut self.fee_growth_global_x,
+                    false => &mut self.fee_growth_global_y,
+                };
+                *global = global.wrapping_add(growth);
+            }
+
+            self.sqrt_price = step.sqrt_price;
+
+            if step.sqrt_price == sqrt_target {
+                if let Some(i) = next {
+                    self.cross(&mut ticks[i], is_x)?;
+                }
+                // the edge of the range is left uncrossed on the way up, its
+                // tick may be initialized in ticks the caller did not pass
+                self.tick = match (next, is_x) {
+                    (Some(_), false) => target_tick,
+                    _ => target_tick - 1,
+                };
+            } else {
+                self.tick = tick_at_sqrt_price(self.sqrt_price)?;
+            }
+        }
+
+        if withdraw == 0 {
+            return Err(MathError::InvalidAmount);
+        }
+
+        Ok(ConcentratedSwap {
+            swap: SwapResult {
+                deposit: amount_in,
+                withdraw: to_u64(withdraw)?,
+                fee: to_u64(fee_total)?,
+            },
+            protocol_fee: protocol_total,
+        })
+    }
+
+    // Adds (delta > 0) or removes liquidity from a position over [tick_lower,
+    // tick_upper), crediting the fees it earned since it was last touched. Returns
+    // what the change costs, rounded up, or pays out, rounded down. A delta of zero
+    // only brings the fees owed up to date.
+    pub fn modify_position(
+        &mut self,
+        ticks: &mut Vec<Tick>,
+        position: &mut Position,
+        tick_lower: i32,
+        tick_upper: i32,
+        delta: i128,
+    ) -> Result<XYAmounts> {
+        if delta != 0 {
+            self.update_tick(ticks, tick_lower, delta, false)?;
+            self.update_tick(ticks, tick_upper, delta, true)?;
+        }
+
+        let (inside_x, inside_y) = self.fee_growth_inside(ticks, tick_lower, tick_upper);
+        position.accrue_fees(inside_x, inside_y)?;
+        position.liquidity = add_delta(position.liquidity, delta)?;
+
+        if delta < 0 {
+            ticks.retain(|tick| tick.liquidity_gross != 0);
+        }
+
+        let amounts = self.amounts_for_liquidity(tick_lower, tick_upper, delta)?;
+
+        if (tick_lower..tick_upper).contains(&self.tick) {
+            self.liquidity = add_delta(self.liquidity, delta)?;
+        }
+
+        Ok(amounts)
+    }
+
+    // Fee growth per unit of liquidity earned inside [tick_lower, tick_upper)
+    pub fn fee_growth_inside(&self, ticks: &[Tick], tick_lower: i32, tick_upper: i32) -> (u128, u128) {
+        let outside = |index: i32| {
+            ticks
+                .binary_search_by_key(&index, |tick| tick.index)
+                .map(|i| (ticks[i].fee_growth_outside_x, ticks[i].fee_growth_outside_y))
+                .unwrap_or((0, 0))
+        };
+        let (lower_x, lower_y) = outside(tick_lower);
+        let (upper_x, upper_y) = outside(tick_upper);
+
+        let inside = |global: u128, lower: u128, upper: u128| {
+            let below = match self.tick >= tick_lower {
+                true => lower,
+                false => global.wrapping_sub(lower),
+            };
+            let above = match self.tick < tick_upper {
+                true => upper,
+                false => global.wrapping_sub(upper),
+            };
+            global.wrapping_sub(below).wrapping_sub(above)
+        };
+
+        (
+            inside(self.fee_growth_global_x, lower_x, upper_x),
+            inside(self.fee_growth_global_y, lower_y, upper_y),
+        )
+    }
+
+    fn cross(&mut self, tick: &mut Tick, is_x: bool) -> Result<()> {
+        tick.fee_growth_outside_x = self.fee_growth_global_x.wrapping_sub(tick.fee_growth_outside_x);
+        tick.fee_growth_outside_y = self.fee_growth_global_y.wrapping_sub(tick.fee_growth_outside_y);
+
+        // moving down leaves the ranges the tick opened
+        let delta = match is_x {
+            true => tick.liquidity_net.checked_neg().ok_or(MathError::Overflow)?,
+            false => tick.liquidity_net,
+        };
+        self.liquidity = add_delta(self.liquidity, delta)?;
+        Ok(())
+    }
+
+    fn update_tick(&self, ticks: &mut Vec<Tick>, index: i32, delta: i128, upper: bool) -> Result<()> {
+        let i = match ticks.binary_search_by_key(&index, |tick| tick.index) {
+            Ok(i) => i,
+            Err(i) => {
+                if delta < 0 {
+                    return Err(MathError::Underflow);
+                }
+
+                // all growth so far is taken to have happened below the current price
+                let (outside_x, outside_y) = match index <= self.tick {
+                    true => (self.fee_growth_global_x, self.fee_growth_global_y),
+                    false => (0, 0),
+                };
+                ticks.insert(i, Tick {
+                    index,
+                    liquidity_net: 0,
+                    liquidity_gross: 0,
+                    fee_growth_outside_x: outside_x,
+                    fee_growth_outside_y: outside_y,
+                });
+                i
+            },
+        };
+
+        let tick = &mut ticks[i];
+        tick.liquidity_gross = add_delta(tick.liquidity_gross, delta)?;
+        if tick.liquidity_gross > i128::MAX as u128 {
+            return Err(MathError::Overflow);
+        }
+
+        tick.liquidity_net = match upper {
+            true => tick.liquidity_net.checked_sub(delta),
+            false => tick.liquidity_net.checked_add(delta),
+        }
+        .ok_or(MathError::Overflow)?;
+
+        Ok(())
+    }
+
+    fn amounts_for_liquidity(&self, tick_lower: i32, tick_upper: i32, delta: i128) -> Result<XYAmounts> {
+        let liquidity = delta.unsigned_abs();
+        let round_up = delta > 0;
+
+        let sqrt_lower = sqrt_price_at_tick(tick_lower)?;
+        let sqrt_upper = sqrt_price_at_tick(tick_upper)?;
+
+        let (x, y) = match self.tick {
+            tick if tick < tick_lower => (amount_x_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?, 0),
+            tick if tick < tick_upper => (
+                amount_x_delta(self.sqrt_price, sqrt_upper, liquidity, round_up)?,
+                amount_y_delta(sqrt_lower, self.sqrt_price, liquidity, round_up)?,
+            ),
+            _ => (0, amount_y_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?),
+        };
+
+        Ok(XYAmounts { x: to_u64(x)?, y: to_u64(y)? })
+    }
+}
+
+impl Position {
+    fn accrue_fees(&mut self, inside_x: u128, inside_y: u128) -> Result<()> {
+        let owed_x = mul_div(self.liquidity, inside_x.wrapping_sub(self.fee_growth_inside_x), Q64, false)?;
+        let owed_y = mul_div(self.liquidity, inside_y.wrapping_sub(self.fee_growth_inside_y), Q64, false)?;
+
+        self.tokens_owed_x = self.tokens_owed_x.checked_add(to_u64(owed_x)?).ok_or(MathError::Overflow)?;
+        self.tokens_owed_y = self.tokens_owed_y.checked_add(to_u64(owed_y)?).ok_or(MathError::Overflow)?;
+        self.fee_growth_inside_x = inside_x;
+        self.fee_growth_inside_y = inside_y;
+        Ok(())
+    }
+}
+
+// A position's range: ordered, inside the tick bounds and on the pool's spacing
+pub fn check_range(tick_lower: i32, tick_upper: i32, tick_spacing: u16) -> Result<()> {
+    let spacing = tick_spacing as i32;
+    let valid = spacing != 0
+        && tick_lower < tick_upper
+        && tick_lower >= MIN_TICK
+        && tick_upper <= MAX_TICK
+        && tick_lower % spacing == 0
+        && tick_upper % spacing == 0;
+
+    match valid {
+        true => Ok(()),
+        false => Err(MathError::InvalidTick),
+    }
+}
+
+// sqrt(1.0001^tick) as Q64.64, by squaring sqrt(1.0001) up
+pub fn sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(MathError::InvalidTick);
+    }
+
+    let mut exponent = tick.unsigned_abs();
+    let mut base = SQRT_BASE_X64;
+    let mut ratio = Q64;
+    while exponent != 0 {
+        if exponent & 1 == 1 {
+            ratio = mul_shr64(ratio, base)?;
+        }
+        exponent >>= 1;
+        if exponent != 0 {
+            base = mul_shr64(base, base)?;
+        }
+    }
+
+    match tick < 0 {
+        true => mul_div(Q64, Q64, ratio, false),
+        false => Ok(ratio),
+    }
+}
+
+// Greatest tick whose sqrt price is at or below sqrt_price
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> Result<i32> {
+    if sqrt_price < sqrt_price_at_tick(MIN_TICK)? || sqrt_price > sqrt_price_at_tick(MAX_TICK)? {
+        return Err(MathError::InvalidTick);
+    }
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        match sqrt_price_at_tick(mid)? <= sqrt_price {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+    Ok(low)
+}
+
+// Price of x in y as Q64.64 from its square root
+pub fn price_from_sqrt_price(sqrt_price: u128) -> Result<u128> {
+    mul_shr64(sqrt_price, sqrt_price)
+}
+
+// x held by `liquidity` between two sqrt prices: L·(√b − √a) / (√a·√b)
+pub fn amount_x_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Result<u128> {
+    let (lower, upper) = (sqrt_a.min(sqrt_b), sqrt_a.max(sqrt_b));
+    if lower == 0 {
+        return Err(MathError::ZeroBalance);
+    }
+
+    let scaled = mul_div(liquidity, upper - lower, upper, round_up)?;
+    mul_div(scaled, Q64, lower, round_up)
+}
+
+// y held by `liquidity` between two sqrt prices: L·(√b − √a)
+pub fn amount_y_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Result<u128> {
+    let (lower, upper) = (sqrt_a.min(sqrt_b), sqrt_a.max(sqrt_b));
+    mul_div(liquidity, upper - lower, Q64, round_up)
+}
+
+// One swap step towards sqrt_target, which it reaches if the input allows. Both
+// next-price formulas round towards the starting price, so the pool never gives
+// out more than the input paid for.
+fn swap_step(
+    sqrt_price: u128,
+    sqrt_target: u128,
+    liquidity: u128,
+    remaining: u128,
+    fee: u16,
+    is_x: bool,
+) -> Result<SwapStep> {
+    let fee = fee as u128;
+    let remaining_less_fee = mul_div(remaining, BPS - fee, BPS, false)?;
+
+    let to_target = match is_x {
+        true => amount_x_delta(sqrt_target, sqrt_price, liquidity, true)?,
+        false => amount_y_delta(sqrt_price, sqrt_target, liquidity, true)?,
+    };
+    let reached = remaining_less_fee >= to_target;
+
+    let (next, amount_in) = match reached {
+        true => (sqrt_target, to_target),
+        false => {
+            let next = match is_x {
+                // L·√P / (L + x·√P)
+                true => {
+                    let denominator = liquidity
+                        .checked_add(mul_div(remaining_less_fee, sqrt_price, Q64, false)?)
+                        .ok_or(MathError::Overflow)?;
+                    mul_div(liquidity, sqrt_price, denominator, true)?.max(sqrt_target)
+                },
+                // √P + y / L
+                false => sqrt_price
+                    .checked_add(mul_div(remaining_less_fee, Q64, liquidity, false)?)
+                    .ok_or(MathError::Overflow)?
+                    .min(sqrt_target),
+            };
+            (next, remaining_less_fee)
+        },
+    };
+
+    let amount_out = match is_x {
+        true => amount_y_delta(next, sqrt_price, liquidity, false)?,
+        false => amount_x_delta(sqrt_price, next, liquidity, false)?,
+    };
+
+    // a step that stops short of the target used all of the input, the rest is fee
+    let fee_amount = match reached {
+        true => mul_div(amount_in, fee, BPS - fee, true)?,
+        false => remaining - amount_in,
+    };
+
+    Ok(SwapStep { sqrt_price: next, amount_in, amount_out, fee: fee_amount })
+}
+
+fn add_delta(liquidity: u128, delta: i128) -> Result<u128> {
+    match delta < 0 {
+        true => liquidity.checked_sub(delta.unsigned_abs()).ok_or(MathError::Underflow),
+        false => liquidity.checked_add(delta as u128).ok_or(MathError::Overflow),
+    }
+}
+
+// a·b / 2^64, rounded down
+fn mul_shr64(a: u128, b: u128) -> Result<u128> {
+    let (high, low) = mul_wide(a, b);
+    if high >> 64 != 0 {
+        return Err(MathError::Overflow);
+    }
+    Ok((high << 64) | (low >> 64))
+}
@@ -0,0 +1,19 @@
+use crate::{mul_div_floor, to_u64, MathError, Result};
+
+// x·y = k
+pub fn invariant(x: u64, y: u64) -> u128 {
+    x as u128 * y as u128
+}
+
+// Output for an input already net of the swap fee, rounded down so k never shrinks
+pub fn amount_out(reserve_in: u64, reserve_out: u64, amount_in: u128) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::ZeroBalance);
+    }
+
+    let new_reserve_in = (reserve_in as u128)
+        .checked_add(amount_in)
+        .ok_or(MathError::Overflow)?;
+
+    to_u64(mul_div_floor(reserve_out as u128, amount_in, new_reserve_in)?)
+}
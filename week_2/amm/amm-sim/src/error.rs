@@ -0,0 +1,37 @@
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    Overflow,
+    Underflow,
+    ZeroBalance,
+    InvalidAmount,
+    InsufficientBalance,
+    InvalidAmp,
+    LiquidityLessThanMinimum,
+    SlippageExceeded,
+    InvalidTick,
+    TickRangeExceeded,
+    InsufficientLiquidity,
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            MathError::Overflow => "overflow",
+            MathError::Underflow => "underflow",
+            MathError::ZeroBalance => "zero balance",
+            MathError::InvalidAmount => "invalid amount",
+            MathError::InsufficientBalance => "insufficient balance",
+            MathError::InvalidAmp => "amplification coefficient out of range",
+            MathError::LiquidityLessThanMinimum => "liquidity less than minimum",
+            MathError::SlippageExceeded => "slippage exceeded",
+            MathError::InvalidTick => "invalid tick",
+            MathError::TickRangeExceeded => "swap ran past the ticks provided",
+            MathError::InsufficientLiquidity => "insufficient liquidity",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for MathError {}
@@ -0,0 +1,143 @@
+// Pool maths for the amm program, free of any Solana dependency.
+//
+// The program prices every swap, deposit and withdraw through these functions,
+// and `Pool` strings them together the same way the instruction handlers do, so
+// pool behaviour can be replayed and property-tested without a validator.
+// Token-2022 transfer fees are applied by the program around these calls and
+// are not modelled here.
+//
+// Every division rounds in the pool's favour: users pay rounded up and receive
+// rounded down.
+
+mod error;
+pub mod concentrated;
+pub mod constant_product;
+pub mod liquidity;
+pub mod pool;
+pub mod stable_swap;
+
+pub use error::MathError;
+pub use liquidity::*;
+pub use pool::Pool;
+pub use stable_swap::StableSwap;
+
+pub type Result<T> = core::result::Result<T, MathError>;
+
+// Fees are in basis points
+pub const BPS: u128 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    ConstantProduct,
+    StableSwap { amp: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapResult {
+    // Taken from the user, fee included
+    pub deposit: u64,
+    // Paid out to the user
+    pub withdraw: u64,
+    // Part of deposit kept by the pool as the swap fee
+    pub fee: u64,
+}
+
+// Prices a swap of amount_in against the curve. The fee, in basis points, is taken
+// out of the input and stays in the pool along with the rest of it.
+pub fn swap(curve: Curve, reserve_in: u64, reserve_out: u64, amount_in: u64, fee: u16) -> Result<SwapResult> {
+    if amount_in == 0 {
+        return Err(MathError::InvalidAmount);
+    }
+
+    let fee_amount = mul_div_ceil(amount_in as u128, fee as u128, BPS)?;
+    let amount_in_after_fee = amount_in as u128 - fee_amount;
+
+    let withdraw = match curve {
+        Curve::ConstantProduct => {
+            constant_product::amount_out(reserve_in, reserve_out, amount_in_after_fee)?
+        },
+        Curve::StableSwap { amp } => {
+            StableSwap::new(amp).amount_out(reserve_in, reserve_out, amount_in_after_fee)?
+        },
+    };
+
+    if withdraw == 0 {
+        return Err(MathError::InvalidAmount);
+    }
+
+    Ok(SwapResult {
+        deposit: amount_in,
+        withdraw,
+        fee: fee_amount as u64,
+    })
+}
+
+pub(crate) fn mul_div_floor(a: u128, b: u128, c: u128) -> Result<u128> {
+    a.checked_mul(b)
+        .ok_or(MathError::Overflow)?
+        .checked_div(c)
+        .ok_or(MathError::ZeroBalance)
+}
+
+pub(crate) fn mul_div_ceil(a: u128, b: u128, c: u128) -> Result<u128> {
+    if c == 0 {
+        return Err(MathError::ZeroBalance);
+    }
+    Ok(a.checked_mul(b).ok_or(MathError::Overflow)?.div_ceil(c))
+}
+
+// Full 256-bit product of two u128s as (high, low)
+pub(crate) fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+
+    let middle = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let low = (lo_lo & MASK) | (middle << 64);
+    let high = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (middle >> 64);
+    (high, low)
+}
+
+// a·b / c with a 256-bit intermediate product
+pub(crate) fn mul_div(a: u128, b: u128, c: u128, round_up: bool) -> Result<u128> {
+    if c == 0 {
+        return Err(MathError::ZeroBalance);
+    }
+
+    let (high, low) = mul_wide(a, b);
+    if high >= c {
+        return Err(MathError::Overflow);
+    }
+
+    let (quotient, remainder) = match high {
+        0 => (low / c, low % c),
+        _ => {
+            // long division, the remainder stays below c so one carry bit is enough
+            let (mut quotient, mut remainder) = (0u128, high);
+            for i in (0..128).rev() {
+                let carry = remainder >> 127;
+                remainder = (remainder << 1) | ((low >> i) & 1);
+                if carry == 1 || remainder >= c {
+                    remainder = remainder.wrapping_sub(c);
+                    quotient |= 1 << i;
+                }
+            }
+            (quotient, remainder)
+        },
+    };
+
+    match round_up && remainder != 0 {
+        true => quotient.checked_add(1).ok_or(MathError::Overflow),
+        false => Ok(quotient),
+    }
+}
+
+pub(crate) fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| MathError::Overflow)
+}
@@ -0,0 +1,152 @@
+use crate::{mul_div_ceil, mul_div_floor, swap, to_u64, Curve, MathError, Result, StableSwap, SwapResult, BPS};
+
+// LP locked forever on the first deposit so the share price can't be inflated
+// by whoever seeds the pool with dust.
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XYAmounts {
+    pub x: u64,
+    pub y: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZapResult {
+    // The part of the input priced as a swap for the other side
+    pub swap: SwapResult,
+    pub lp: u64,
+}
+
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    let mut x = n;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+// LP for the first deposit, before MINIMUM_LIQUIDITY is taken out: the pool
+// invariant, sqrt(k) for constant product and D for stable swap
+pub fn initial_liquidity(curve: Curve, x: u64, y: u64) -> Result<u64> {
+    if x == 0 || y == 0 {
+        return Err(MathError::InvalidAmount);
+    }
+
+    let invariant = match curve {
+        Curve::ConstantProduct => isqrt(x as u128 * y as u128),
+        Curve::StableSwap { amp } => StableSwap::new(amp).compute_d(x, y)?,
+    };
+    let liquidity = to_u64(invariant)?;
+
+    if liquidity <= MINIMUM_LIQUIDITY {
+        return Err(MathError::LiquidityLessThanMinimum);
+    }
+    Ok(liquidity)
+}
+
+// What a deposit for `lp` LP tokens costs, rounded up
+pub fn deposit_amounts(reserve_x: u64, reserve_y: u64, supply: u64, lp: u64) -> Result<XYAmounts> {
+    if supply == 0 {
+        return Err(MathError::ZeroBalance);
+    }
+
+    Ok(XYAmounts {
+        x: to_u64(mul_div_ceil(reserve_x as u128, lp as u128, supply as u128)?)?,
+        y: to_u64(mul_div_ceil(reserve_y as u128, lp as u128, supply as u128)?)?,
+    })
+}
+
+// What burning `lp` LP tokens pays out, rounded down
+pub fn withdraw_amounts(reserve_x: u64, reserve_y: u64, supply: u64, lp: u64) -> Result<XYAmounts> {
+    if lp > supply {
+        return Err(MathError::InsufficientBalance);
+    }
+
+    Ok(XYAmounts {
+        x: to_u64(mul_div_floor(reserve_x as u128, lp as u128, supply as u128)?)?,
+        y: to_u64(mul_div_floor(reserve_y as u128, lp as u128, supply as u128)?)?,
+    })
+}
+
+// Protocol share of a swap fee, protocol_fee in basis points of the fee
+pub fn protocol_fee(swap_fee: u64, protocol_fee: u16) -> Result<u64> {
+    to_u64(mul_div_floor(swap_fee as u128, protocol_fee as u128, BPS)?)
+}
+
+// Fee on a flash loan, at the pool's swap fee and rounded up so a loan is never free
+pub fn flash_loan_fee(amount: u64, fee: u16) -> Result<u64> {
+    to_u64(mul_div_ceil(amount as u128, fee as u128, BPS)?)
+}
+
+// Single-sided deposit: all of amount_in goes into the pool, part of it priced as
+// a swap for the other side, and LP is minted for the balanced deposit that leaves.
+// Nothing moves in the other direction.
+pub fn deposit_single(
+    curve: Curve,
+    reserve_in: u64,
+    reserve_out: u64,
+    supply: u64,
+    fee: u16,
+    amount_in: u64,
+) -> Result<ZapResult> {
+    if supply == 0 {
+        return Err(MathError::ZeroBalance);
+    }
+
+    let swapped = zap_swap_amount(curve, reserve_in, reserve_out, fee, amount_in);
+    let result = swap(curve, reserve_in, reserve_out, swapped, fee)?;
+
+    // Reserves after the swap, with the rest of the input and the swap output
+    // added back in proportion. The smaller side bounds the LP.
+    let kept = (amount_in - swapped) as u128;
+    let remaining_out = reserve_out
+        .checked_sub(result.withdraw)
+        .filter(|remaining| *remaining != 0)
+        .ok_or(MathError::InsufficientBalance)?;
+
+    let lp_from_in = mul_div_floor(supply as u128, kept, reserve_in as u128 + swapped as u128)?;
+    let lp_from_out = mul_div_floor(supply as u128, result.withdraw as u128, remaining_out as u128)?;
+    let lp = to_u64(lp_from_in.min(lp_from_out))?;
+
+    if lp == 0 {
+        return Err(MathError::InvalidAmount);
+    }
+
+    Ok(ZapResult { swap: result, lp })
+}
+
+// Largest part of `amount` that, once swapped, leaves the remainder in at least
+// the pool's new ratio. Found by bisection so it holds for either curve.
+fn zap_swap_amount(curve: Curve, reserve_in: u64, reserve_out: u64, fee: u16, amount: u64) -> u64 {
+    let (mut low, mut high) = (0u64, amount);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+
+        // amounts too small for the curve to return anything swap for nothing
+        let out = swap(curve, reserve_in, reserve_out, mid, fee)
+            .map(|result| result.withdraw as u128)
+            .unwrap_or(0);
+
+        // (amount - mid) / (reserve_in + mid) >= out / (reserve_out - out)
+        let balanced = match (reserve_out as u128).checked_sub(out) {
+            Some(remaining_out) if remaining_out != 0 => {
+                (amount - mid) as u128 * remaining_out
+                    >= out.saturating_mul(reserve_in as u128 + mid as u128)
+            },
+            _ => false,
+        };
+
+        match balanced {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+
+    low
+}
@@ -0,0 +1,172 @@
+use crate::{
+    deposit_amounts, deposit_single, initial_liquidity, protocol_fee, swap, withdraw_amounts,
+    Curve, MathError, Result, SwapResult, XYAmounts, MINIMUM_LIQUIDITY,
+};
+
+// Pool state as the program sees it: the config fields the maths reads, the two
+// vault balances and the LP supply. Each method follows its instruction handler
+// step for step, minus the token transfers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pool {
+    pub curve: Curve,
+    pub fee: u16,
+    pub protocol_fee: u16,
+    pub vault_x: u64,
+    pub vault_y: u64,
+    pub protocol_fees_x: u64,
+    pub protocol_fees_y: u64,
+    pub lp_supply: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositResult {
+    pub x: u64,
+    pub y: u64,
+    // LP minted to the depositor, the locked MINIMUM_LIQUIDITY not included
+    pub lp: u64,
+}
+
+impl Pool {
+    pub fn new(curve: Curve, fee: u16, protocol_fee: u16) -> Self {
+        Self {
+            curve,
+            fee,
+            protocol_fee,
+            vault_x: 0,
+            vault_y: 0,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            lp_supply: 0,
+        }
+    }
+
+    // Vault balances minus the protocol fees waiting to be collected
+    pub fn reserves(&self) -> Result<(u64, u64)> {
+        let x = self.vault_x.checked_sub(self.protocol_fees_x).ok_or(MathError::Underflow)?;
+        let y = self.vault_y.checked_sub(self.protocol_fees_y).ok_or(MathError::Underflow)?;
+        Ok((x, y))
+    }
+
+    // `Deposit::deposit`. On the first deposit max_x and max_y are taken as given
+    // and `amount` is the least LP accepted, after that `amount` is the LP wanted.
+    pub fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<DepositResult> {
+        if amount == 0 {
+            return Err(MathError::InvalidAmount);
+        }
+
+        let (reserve_x, reserve_y) = self.reserves()?;
+
+        let result = match self.lp_supply == 0 {
+            true => {
+                let liquidity = initial_liquidity(self.curve, max_x, max_y)?;
+                let lp = liquidity - MINIMUM_LIQUIDITY;
+                if lp < amount {
+                    return Err(MathError::SlippageExceeded);
+                }
+                DepositResult { x: max_x, y: max_y, lp }
+            },
+            false => {
+                let amounts = deposit_amounts(reserve_x, reserve_y, self.lp_supply, amount)?;
+                DepositResult { x: amounts.x, y: amounts.y, lp: amount }
+            },
+        };
+
+        if result.x > max_x || result.y > max_y {
+            return Err(MathError::SlippageExceeded);
+        }
+
+        let minted = match self.lp_supply == 0 {
+            true => result.lp + MINIMUM_LIQUIDITY,
+            false => result.lp,
+        };
+
+        self.vault_x = self.vault_x.checked_add(result.x).ok_or(MathError::Overflow)?;
+        self.vault_y = self.vault_y.checked_add(result.y).ok_or(MathError::Overflow)?;
+        self.lp_supply = self.lp_supply.checked_add(minted).ok_or(MathError::Overflow)?;
+
+        Ok(result)
+    }
+
+    // `Withdraw::withdraw`
+    pub fn withdraw(&mut self, lp_amount: u64, min_x: u64, min_y: u64) -> Result<XYAmounts> {
+        if lp_amount == 0 {
+            return Err(MathError::InvalidAmount);
+        }
+
+        let (reserve_x, reserve_y) = self.reserves()?;
+        let amounts = withdraw_amounts(reserve_x, reserve_y, self.lp_supply, lp_amount)?;
+
+        if amounts.x < min_x || amounts.y < min_y {
+            return Err(MathError::SlippageExceeded);
+        }
+
+        self.vault_x -= amounts.x;
+        self.vault_y -= amounts.y;
+        self.lp_supply -= lp_amount;
+
+        Ok(amounts)
+    }
+
+    // `Swap::swap`, is_x: pays in x and receives y
+    pub fn swap(&mut self, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<SwapResult> {
+        let (reserve_x, reserve_y) = self.reserves()?;
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+
+        let result = swap(self.curve, reserve_in, reserve_out, amount_in, self.fee)?;
+        if result.withdraw < min_amount_out {
+            return Err(MathError::SlippageExceeded);
+        }
+
+        self.settle_swap(is_x, result)?;
+        Ok(result)
+    }
+
+    // `DepositSingle::deposit_single`
+    pub fn deposit_single(&mut self, is_x: bool, amount_in: u64, min_lp_out: u64) -> Result<u64> {
+        let (reserve_x, reserve_y) = self.reserves()?;
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+
+        let zap = deposit_single(self.curve, reserve_in, reserve_out, self.lp_supply, self.fee, amount_in)?;
+        if zap.lp < min_lp_out {
+            return Err(MathError::SlippageExceeded);
+        }
+
+        // the swap output never leaves the pool
+        let vault_in = match is_x {
+            true => &mut self.vault_x,
+            false => &mut self.vault_y,
+        };
+        *vault_in = vault_in.checked_add(amount_in).ok_or(MathError::Overflow)?;
+        self.accrue_protocol_fee(is_x, zap.swap.fee)?;
+        self.lp_supply = self.lp_supply.checked_add(zap.lp).ok_or(MathError::Overflow)?;
+
+        Ok(zap.lp)
+    }
+
+    fn settle_swap(&mut self, is_x: bool, result: SwapResult) -> Result<()> {
+        let (vault_in, vault_out) = match is_x {
+            true => (&mut self.vault_x, &mut self.vault_y),
+            false => (&mut self.vault_y, &mut self.vault_x),
+        };
+        *vault_in = vault_in.checked_add(result.deposit).ok_or(MathError::Overflow)?;
+        *vault_out = vault_out.checked_sub(result.withdraw).ok_or(MathError::Underflow)?;
+
+        self.accrue_protocol_fee(is_x, result.fee)
+    }
+
+    fn accrue_protocol_fee(&mut self, is_x: bool, swap_fee: u64) -> Result<()> {
+        let fee = protocol_fee(swap_fee, self.protocol_fee)?;
+        let accrued = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *accrued = accrued.checked_add(fee).ok_or(MathError::Overflow)?;
+        Ok(())
+    }
+}
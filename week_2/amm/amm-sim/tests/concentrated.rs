@@ -0,0 +1,146 @@
+use amm_sim::{
+    concentrated::{
+        sqrt_price_at_tick, tick_at_sqrt_price, PoolState, Position, Tick, MAX_TICK, MIN_TICK,
+    },
+    MathError,
+};
+use proptest::prelude::*;
+
+const SPACING: i32 = 10;
+
+struct Seeded {
+    pool: PoolState,
+    ticks: Vec<Tick>,
+    positions: Vec<(i32, i32, Position)>,
+    vault_x: i128,
+    vault_y: i128,
+}
+
+// A pool at tick 0 with a wide position and a narrow one around the price, and
+// the vault balances their deposits left behind
+fn seeded_pool(liquidity: u128) -> Seeded {
+    let mut pool = PoolState::new(sqrt_price_at_tick(0).unwrap()).unwrap();
+    let mut ticks = Vec::new();
+    let mut positions = vec![(-1_000, 1_000, Position::default()), (-50, 50, Position::default())];
+    let (mut vault_x, mut vault_y) = (0i128, 0i128);
+
+    for (lower, upper, position) in positions.iter_mut() {
+        let paid = pool
+            .modify_position(&mut ticks, position, *lower, *upper, liquidity as i128)
+            .unwrap();
+        vault_x += paid.x as i128;
+        vault_y += paid.y as i128;
+    }
+
+    Seeded { pool, ticks, positions, vault_x, vault_y }
+}
+
+proptest! {
+    #[test]
+    fn tick_round_trips_through_sqrt_price(tick in MIN_TICK..MAX_TICK) {
+        let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+        prop_assert!(sqrt_price < sqrt_price_at_tick(tick + 1).unwrap());
+        prop_assert_eq!(tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+        prop_assert_eq!(tick_at_sqrt_price(sqrt_price_at_tick(tick + 1).unwrap() - 1).unwrap(), tick);
+    }
+
+    #[test]
+    fn add_then_remove_liquidity_never_profits(
+        start in -2_000i32..2_000,
+        lower in -200i32..200,
+        width in 1i32..200,
+        liquidity in 1u128..1 << 64,
+    ) {
+        let (lower, upper) = (lower * SPACING, (lower + width) * SPACING);
+        let mut pool = PoolState::new(sqrt_price_at_tick(start).unwrap()).unwrap();
+        let mut ticks = Vec::new();
+        let mut position = Position::default();
+
+        let paid = pool.modify_position(&mut ticks, &mut position, lower, upper, liquidity as i128).unwrap();
+        let received = pool.modify_position(&mut ticks, &mut position, lower, upper, -(liquidity as i128)).unwrap();
+
+        prop_assert!(received.x <= paid.x);
+        prop_assert!(received.y <= paid.y);
+        prop_assert!(ticks.is_empty());
+        prop_assert_eq!(pool.liquidity, 0);
+    }
+
+    #[test]
+    fn pool_stays_solvent_through_swaps(
+        liquidity in 1u128 << 20..1 << 40,
+        fee in 0u16..1_000,
+        protocol_fee in 0u16..=5_000,
+        swaps in prop::collection::vec((any::<bool>(), 1u64..1 << 24), 1..16),
+    ) {
+        let Seeded { mut pool, mut ticks, mut positions, mut vault_x, mut vault_y } = seeded_pool(liquidity);
+        let (mut protocol_x, mut protocol_y) = (0i128, 0i128);
+
+        for (is_x, amount) in swaps {
+            let before = (pool, ticks.clone());
+            let Ok(result) = pool.swap(&mut ticks, MIN_TICK..=MAX_TICK, is_x, amount, fee, protocol_fee) else {
+                // a failed swap leaves the pool as it was, like a failed transaction
+                (pool, ticks) = before;
+                continue;
+            };
+
+            let (deposit, withdraw) = (result.swap.deposit as i128, result.swap.withdraw as i128);
+            match is_x {
+                true => {
+                    vault_x += deposit;
+                    vault_y -= withdraw;
+                    protocol_x += result.protocol_fee as i128;
+                },
+                false => {
+                    vault_y += deposit;
+                    vault_x -= withdraw;
+                    protocol_y += result.protocol_fee as i128;
+                },
+            }
+        }
+
+        // every position leaves with its liquidity and fees
+        for (lower, upper, position) in positions.iter_mut() {
+            let liquidity = position.liquidity as i128;
+            let received = pool.modify_position(&mut ticks, position, *lower, *upper, -liquidity).unwrap();
+            vault_x -= received.x as i128 + position.tokens_owed_x as i128;
+            vault_y -= received.y as i128 + position.tokens_owed_y as i128;
+        }
+
+        prop_assert!(vault_x >= protocol_x, "x short by {}", protocol_x - vault_x);
+        prop_assert!(vault_y >= protocol_y, "y short by {}", protocol_y - vault_y);
+        prop_assert_eq!(pool.liquidity, 0);
+    }
+
+    #[test]
+    fn swap_over_part_of_the_ticks_matches_or_stops_at_the_edge(
+        liquidity in 1u128 << 20..1 << 40,
+        below in 1i32..120,
+        above in 0i32..120,
+        is_x in any::<bool>(),
+        amount in 1u64..1 << 24,
+    ) {
+        let Seeded { pool, ticks, .. } = seeded_pool(liquidity);
+        let range = -below * SPACING..=above * SPACING - 1;
+
+        let (mut full_pool, mut full_ticks) = (pool, ticks.clone());
+        let full = full_pool.swap(&mut full_ticks, MIN_TICK..=MAX_TICK, is_x, amount, 30, 0);
+
+        // only the ticks inside the range are handed over, as from a run of tick arrays
+        let (mut part_pool, mut part_ticks) = (pool, ticks);
+        part_ticks.retain(|tick| range.contains(&tick.index));
+        let part = part_pool.swap(&mut part_ticks, range.clone(), is_x, amount, 30, 0);
+
+        match part {
+            Ok(result) => {
+                prop_assert_eq!(Ok(result), full);
+                prop_assert_eq!(part_pool, full_pool);
+                full_ticks.retain(|tick| range.contains(&tick.index));
+                prop_assert_eq!(part_ticks, full_ticks);
+            },
+            Err(error) => prop_assert!(
+                error == MathError::TickRangeExceeded || Err(error) == full,
+                "{:?} against {:?}", error, full
+            ),
+        }
+    }
+}
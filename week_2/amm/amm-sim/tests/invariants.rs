@@ -0,0 +1,177 @@
+use amm_sim::{constant_product, Curve, Pool, StableSwap};
+use proptest::prelude::*;
+
+// Reserves and amounts stay under 2^30 so k·L² fits in a u128 when share values
+// are compared across a run of operations
+const MAX_SEED: u64 = 1 << 24;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Swap { is_x: bool, amount: u64 },
+    Deposit { lp: u64 },
+    Withdraw { lp: u64 },
+    DepositSingle { is_x: bool, amount: u64 },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (any::<bool>(), 1..MAX_SEED).prop_map(|(is_x, amount)| Op::Swap { is_x, amount }),
+        (1..MAX_SEED).prop_map(|lp| Op::Deposit { lp }),
+        (1..MAX_SEED).prop_map(|lp| Op::Withdraw { lp }),
+        (any::<bool>(), 1..MAX_SEED).prop_map(|(is_x, amount)| Op::DepositSingle { is_x, amount }),
+    ]
+}
+
+fn curve() -> impl Strategy<Value = Curve> {
+    prop_oneof![
+        Just(Curve::ConstantProduct),
+        (1u64..10_000).prop_map(|amp| Curve::StableSwap { amp }),
+    ]
+}
+
+// Any reserve, with dust as likely as a full one so lopsided pools come up
+fn reserve() -> impl Strategy<Value = u64> {
+    prop_oneof![1u64..1_000, 1u64..u64::MAX]
+}
+
+fn seeded_pool(curve: Curve, fee: u16, protocol_fee: u16, x: u64, y: u64) -> Option<Pool> {
+    let mut pool = Pool::new(curve, fee, protocol_fee);
+    pool.deposit(1, x, y).ok()?;
+    Some(pool)
+}
+
+fn invariant(pool: &Pool) -> u128 {
+    let (x, y) = pool.reserves().unwrap();
+    match pool.curve {
+        Curve::ConstantProduct => constant_product::invariant(x, y),
+        Curve::StableSwap { amp } => StableSwap::new(amp).compute_d(x, y).unwrap(),
+    }
+}
+
+fn apply(pool: &mut Pool, op: &Op) {
+    // failed operations leave the pool as it was, like a failed transaction
+    let before = *pool;
+    let applied = match *op {
+        Op::Swap { is_x, amount } => pool.swap(is_x, amount, 0).map(|_| ()),
+        Op::Deposit { lp } => pool.deposit(lp, u64::MAX, u64::MAX).map(|_| ()),
+        Op::Withdraw { lp } => pool.withdraw(lp.min(pool.lp_supply - 1_000), 0, 0).map(|_| ()),
+        Op::DepositSingle { is_x, amount } => pool.deposit_single(is_x, amount, 0).map(|_| ()),
+    };
+    if applied.is_err() {
+        *pool = before;
+    }
+}
+
+proptest! {
+    #[test]
+    fn fee_bearing_swap_never_decreases_invariant(
+        curve in curve(),
+        fee in 1u16..1_000,
+        protocol_fee in 0u16..=5_000,
+        x in 1_000_000..MAX_SEED,
+        y in 1_000_000..MAX_SEED,
+        is_x in any::<bool>(),
+        amount in 1..MAX_SEED,
+    ) {
+        let mut pool = seeded_pool(curve, fee, protocol_fee, x, y).unwrap();
+        let before = invariant(&pool);
+
+        if pool.swap(is_x, amount, 0).is_ok() {
+            prop_assert!(invariant(&pool) >= before);
+        }
+    }
+
+    #[test]
+    fn swap_output_rounds_down(
+        fee in 0u16..1_000,
+        x in 1..u64::MAX / 4,
+        y in 1..u64::MAX / 4,
+        amount in 1..u64::MAX / 4,
+    ) {
+        if let Ok(result) = amm_sim::swap(Curve::ConstantProduct, x, y, amount, fee) {
+            let net = (result.deposit - result.fee) as u128;
+            // exact output is y·net / (x + net)
+            prop_assert!(result.withdraw as u128 * (x as u128 + net) <= y as u128 * net);
+            prop_assert!(result.fee as u128 * 10_000 >= amount as u128 * fee as u128);
+        }
+    }
+
+    #[test]
+    fn deposit_rounds_up_and_withdraw_rounds_down(
+        x in 1..u64::MAX / 2,
+        y in 1..u64::MAX / 2,
+        supply in 1..u64::MAX / 2,
+        lp in 1..u64::MAX / 2,
+    ) {
+        if let Ok(amounts) = amm_sim::deposit_amounts(x, y, supply, lp) {
+            prop_assert!(amounts.x as u128 * supply as u128 >= x as u128 * lp as u128);
+            prop_assert!(amounts.y as u128 * supply as u128 >= y as u128 * lp as u128);
+        }
+        if let Ok(amounts) = amm_sim::withdraw_amounts(x, y, supply, lp) {
+            prop_assert!(amounts.x as u128 * supply as u128 <= x as u128 * lp as u128);
+            prop_assert!(amounts.y as u128 * supply as u128 <= y as u128 * lp as u128);
+        }
+    }
+
+    #[test]
+    fn deposit_then_withdraw_never_profits(
+        curve in curve(),
+        x in 1_000_000..MAX_SEED,
+        y in 1_000_000..MAX_SEED,
+        lp in 1..MAX_SEED,
+    ) {
+        let mut pool = seeded_pool(curve, 30, 0, x, y).unwrap();
+
+        let paid = pool.deposit(lp, u64::MAX, u64::MAX).unwrap();
+        let received = pool.withdraw(lp, 0, 0).unwrap();
+
+        prop_assert!(received.x <= paid.x);
+        prop_assert!(received.y <= paid.y);
+    }
+
+    #[test]
+    fn stable_swap_d_is_symmetric_and_converges(
+        amp in 1u64..10_000,
+        x in reserve(),
+        y in reserve(),
+    ) {
+        let curve = StableSwap::new(amp);
+        let d = curve.compute_d(x, y);
+
+        prop_assert!(d.is_ok(), "no D for {} and {}: {:?}", x, y, d);
+        prop_assert_eq!(d, curve.compute_d(y, x));
+    }
+
+    #[test]
+    fn lp_share_value_never_decreases(
+        fee in 1u16..1_000,
+        protocol_fee in 0u16..=5_000,
+        x in 1_000_000..MAX_SEED,
+        y in 1_000_000..MAX_SEED,
+        ops in prop::collection::vec(op(), 1..32),
+    ) {
+        let mut pool = seeded_pool(Curve::ConstantProduct, fee, protocol_fee, x, y).unwrap();
+
+        for op in &ops {
+            let (k, supply) = (invariant(&pool), pool.lp_supply as u128);
+            apply(&mut pool, op);
+            let (k_after, supply_after) = (invariant(&pool), pool.lp_supply as u128);
+
+            // sqrt(k) / L never falls: k' · L² >= k · L'²
+            prop_assert!(
+                k_after * supply * supply >= k * supply_after * supply_after,
+                "{:?} lowered the LP share value", op
+            );
+        }
+    }
+}
+
+// Found by fee_bearing_swap_never_decreases_invariant: a swap that leaves a
+// stable pool this lopsided used to stop Newton's method from converging on D
+#[test]
+fn stable_swap_d_converges_on_a_lopsided_pool() {
+    let curve = StableSwap::new(4520);
+
+    assert_eq!(curve.compute_d(14248108, 4), Ok(3534586));
+    assert_eq!(curve.compute_d(4, 14248108), Ok(3534586));
+}
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use amm_sim::MathError;
+
+#[error_code]
+pub enum AmmError {
+    #[msg("This pool is locked.")]
+    PoolLocked,
+    #[msg("Slippage exceeded.")]
+    SlippageExceeded,
+    #[msg("Overflow detected.")]
+    Overflow,
+    #[msg("Underflow detected.")]
+    Underflow,
+    #[msg("Fee is greater than the maximum allowed.")]
+    InvalidFee,
+    #[msg("Invalid update authority.")]
+    InvalidAuthority,
+    #[msg("No update authority set.")]
+    NoAuthoritySet,
+    #[msg("Invalid amount.")]
+    InvalidAmount,
+    #[msg("Actual liquidity is less than minimum.")]
+    LiquidityLessThanMinimum,
+    #[msg("Invalid precision.")]
+    InvalidPrecision,
+    #[msg("Insufficient balance.")]
+    InsufficientBalance,
+    #[msg("Zero balance.")]
+    ZeroBalance,
+    #[msg("Pool mints must be distinct and ordered with mint_x < mint_y.")]
+    UnsortedMints,
+    #[msg("Amplification coefficient is out of range.")]
+    InvalidAmp,
+    #[msg("Amp ramp is too short or changes the amplification too much.")]
+    InvalidRamp,
+    #[msg("This pool does not use the stable swap curve.")]
+    NotStableSwap,
+    #[msg("Route accounts do not form a valid path.")]
+    InvalidRoute,
+    #[msg("A flash loan is open on this pool.")]
+    FlashLoanActive,
+    #[msg("No flash loan is open on this pool.")]
+    NoFlashLoan,
+    #[msg("Flash loan is not repaid later in this transaction.")]
+    MissingFlashRepay,
+    #[msg("Flash loans cannot be taken or repaid through CPI.")]
+    FlashLoanCpi,
+    #[msg("Token account mint does not match the pool side.")]
+    InvalidMint,
+    #[msg("Concentrated liquidity pools are traded and funded through positions.")]
+    ConcentratedPool,
+    #[msg("This pool does not use concentrated liquidity.")]
+    NotConcentrated,
+    #[msg("Tick or tick range is invalid for this pool.")]
+    InvalidTick,
+    #[msg("A tick array the instruction needs was not passed.")]
+    TickArrayMissing,
+    #[msg("Not enough liquidity in range to fill the swap.")]
+    InsufficientLiquidity,
+    #[msg("Fee recipient does not match the pool's configured recipient.")]
+    InvalidFeeRecipient,
+    #[msg("Tick arrays must belong to the pool, appear once and follow on from each other.")]
+    InvalidTickArray,
+}
+
+impl From<MathError> for AmmError {
+    fn from(error: MathError) -> AmmError {
+        match error {
+            MathError::Overflow => AmmError::Overflow,
+            MathError::Underflow => AmmError::Underflow,
+            MathError::ZeroBalance => AmmError::ZeroBalance,
+            MathError::InvalidAmount => AmmError::InvalidAmount,
+            MathError::InsufficientBalance => AmmError::InsufficientBalance,
+            MathError::InvalidAmp => AmmError::InvalidAmp,
+            MathError::LiquidityLessThanMinimum => AmmError::LiquidityLessThanMinimum,
+            MathError::SlippageExceeded => AmmError::SlippageExceeded,
+            MathError::InvalidTick => AmmError::InvalidTick,
+            MathError::TickRangeExceeded => AmmError::TickArrayMissing,
+            MathError::InsufficientLiquidity => AmmError::InsufficientLiquidity,
+        }
+    }
+}
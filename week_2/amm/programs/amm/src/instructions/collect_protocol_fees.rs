@@ -0,0 +1,142 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}
+};
+
+use crate::error::AmmError;
+use crate::state::Config;
+
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        has_one = fee_recipient @ AmmError::InvalidFeeRecipient,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: only owns the fee accounts and must be config.fee_recipient, which
+    /// can be a PDA or multisig vault as well as a wallet
+    pub fee_recipient: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_x,
+        associated_token::authority = fee_recipient,
+        associated_token::token_program = token_program_x
+    )]
+    pub fee_recipient_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_y,
+        associated_token::authority = fee_recipient,
+        associated_token::token_program = token_program_y
+    )]
+    pub fee_recipient_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CollectProtocolFees<'info> {
+    // Anyone can crank this, the fees can only ever go to config.fee_recipient
+    pub fn collect_protocol_fees(&mut self) -> Result<()> {
+
+        let fees_x = self.config.protocol_fees_x;
+        let fees_y = self.config.protocol_fees_y;
+
+        self.config.protocol_fees_x = 0;
+        self.config.protocol_fees_y = 0;
+
+        if fees_x != 0 {
+            self.withdraw_token(true, fees_x)?;
+        }
+        if fees_y != 0 {
+            self.withdraw_token(false, fees_y)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.fee_recipient_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.fee_recipient_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"config"[..],
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, decimals)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}
+};
+
+use crate::error::AmmError;
+use crate::state::{Config, Position, TickArrays};
+use crate::transfer_fee::amount_after_fee;
+
+
+#[derive(Accounts)]
+pub struct DecreaseLiquidity<'info> {
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = config,
+        seeds = [
+            b"position",
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            position.tick_lower.to_le_bytes().as_ref(),
+            position.tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program_x
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program_y
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> DecreaseLiquidity<'info> {
+    // Takes `liquidity` out of the position and pays the owner everything it is
+    // owed, the withdrawn liquidity and the fees earned. A liquidity of zero only
+    // collects fees. The position is closed once it is empty. remaining_accounts
+    // are the tick arrays holding the position's two ticks, as for increase_liquidity.
+    pub fn decrease_liquidity(&mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        liquidity: u128,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<()> {
+
+        require!(!self.config.locked, AmmError::PoolLocked);
+
+        let mut tick_arrays = TickArrays::load(&self.config, remaining_accounts)?;
+        require!(
+            tick_arrays.holds(self.position.tick_lower) && tick_arrays.holds(self.position.tick_upper),
+            AmmError::TickArrayMissing
+        );
+
+        self.config.update_concentrated_oracle()?;
+
+        let mut state = self.config.pool_state()?;
+        let mut ticks = tick_arrays.ticks();
+        let mut position = self.position.load();
+
+        let delta = i128::try_from(liquidity)
+            .map_err(|_| AmmError::Overflow)?
+            .checked_neg()
+            .ok_or(AmmError::Overflow)?;
+        let amounts = state
+            .modify_position(&mut ticks, &mut position, self.position.tick_lower, self.position.tick_upper, delta)
+            .map_err(AmmError::from)?;
+
+        let owed_x = position.tokens_owed_x.checked_add(amounts.x).ok_or(AmmError::Overflow)?;
+        let owed_y = position.tokens_owed_y.checked_add(amounts.y).ok_or(AmmError::Overflow)?;
+
+        // minimums are checked against what reaches the owner, net of any transfer fee
+        let received_x = amount_after_fee(&self.mint_x, owed_x)?;
+        let received_y = amount_after_fee(&self.mint_y, owed_y)?;
+        require!(received_x >= min_x && received_y >= min_y, AmmError::SlippageExceeded);
+
+        position.tokens_owed_x = 0;
+        position.tokens_owed_y = 0;
+
+        self.config.set_pool_state(&state);
+        tick_arrays.store(&ticks)?;
+        self.position.store(&position);
+
+        if owed_x != 0 {
+            self.withdraw_token(true, owed_x)?;
+        }
+        if owed_y != 0 {
+            self.withdraw_token(false, owed_y)?;
+        }
+
+        if position.liquidity == 0 {
+            self.position.close(self.owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.user_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.user_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"config"[..],
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, decimals)?;
+        Ok(())
+    }
+}
@@ -1,57 +1,79 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount, Transfer, transfer, MintTo, mint_to}
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked, MintTo, mint_to}
 };
 
-use constant_product_curve::ConstantProduct;
+use amm_sim::{deposit_amounts, initial_liquidity};
+use crate::error::AmmError;
 use crate::state::Config;
+use crate::transfer_fee::{amount_after_fee, amount_before_fee};
+
+pub use amm_sim::MINIMUM_LIQUIDITY;
 
 
 #[derive(Accounts)]
-#[instruction(seed: u64)]
 pub struct Deposit<'info> {
 
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
 
     #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
+        mint::token_program = token_program,
 
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
 
     #[account(
+        mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         associated_token::mint = mint_y,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
-        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
         bump = config.config_bump,
     )]
     pub config: Account<'info, Config>,
@@ -60,13 +82,26 @@ pub struct Deposit<'info> {
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+
+    // Holds the MINIMUM_LIQUIDITY dead shares, the program never transfers out of it
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_lp,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub locked_lp: InterfaceAccount<'info, TokenAccount>,
 
     pub  system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
 }
 
 impl <'info> Deposit<'info> {
@@ -76,66 +111,106 @@ impl <'info> Deposit<'info> {
         max_y: u64,
     ) -> Result<()> {
         
-        assert!(amount != 0, "Amount must be greater than 0");
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount != 0, AmmError::InvalidAmount);
 
-        let (x, y) = match self.mint_lp.supply == 0 && self.vault_x.amount == 0 {
-            true => (max_x, max_y),
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config.update_oracle(reserve_x, reserve_y)?;
+
+        let is_first_deposit = self.mint_lp.supply == 0;
+
+        // On the first deposit the pool takes max_x/max_y as given and `amount`
+        // is the least LP the depositor accepts back. x and y are what the user
+        // sends, transfer fees included.
+        let (x, y, lp_amount) = match is_first_deposit {
+            true => {
+                let received_x = amount_after_fee(&self.mint_x, max_x)?;
+                let received_y = amount_after_fee(&self.mint_y, max_y)?;
+
+                // Initial LP is the pool invariant: sqrt(k) for constant product, D for stable swap
+                let curve = self.config.curve_at(Clock::get()?.unix_timestamp)?;
+                let liquidity = initial_liquidity(curve, received_x, received_y).map_err(AmmError::from)?;
+
+                let lp_amount = liquidity - MINIMUM_LIQUIDITY;
+                require!(lp_amount >= amount, AmmError::SlippageExceeded);
+
+                (max_x, max_y, lp_amount)
+            },
             false => {
-                let amounts =  ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount, 
-                    self.vault_y.amount, 
-                    self.mint_lp.supply, 
-                    amount, 
-                    6
-                ).unwrap();
-                (amounts.x, amounts.y)
+                let amounts = deposit_amounts(
+                    reserve_x,
+                    reserve_y,
+                    self.mint_lp.supply,
+                    amount,
+                ).map_err(AmmError::from)?;
+                (
+                    amount_before_fee(&self.mint_x, amounts.x)?,
+                    amount_before_fee(&self.mint_y, amounts.y)?,
+                    amount,
+                )
             },
         };
 
-        assert!(x <= max_x && y <= max_y, "Amount is greater than max");
+        require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
 
         self.deposit_token(true, x)?;
         self.deposit_token(false, y)?;
-        
+
+        if is_first_deposit {
+            self.mint_lp_tokens(self.locked_lp.to_account_info(), MINIMUM_LIQUIDITY)?;
+        }
+        self.mint_lp_tokens(self.user_lp.to_account_info(), lp_amount)?;
 
         Ok(())
     }
 
     pub fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
 
-        let (from, to) = match is_x {
-            true => (self.user_x.to_account_info(), self.vault_x.to_account_info()),
-            false => (self.user_y.to_account_info(), self.vault_y.to_account_info())
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.user_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.user_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
         };
 
-
-        let cpi_program = self.token_program.to_account_info();
-
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint,
             to,
             authority: self.user.to_account_info(),
         };
 
         let ctx = CpiContext::new(cpi_program, cpi_accounts);
 
-        transfer(ctx, amount)?;
+        transfer_checked(ctx, amount, decimals)?;
         Ok(())
     }
 
-    pub fn mint_lp_tokens(&self, amount: u64) -> Result<()> {
+    pub fn mint_lp_tokens(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
 
         let cpi_program = self.token_program.to_account_info();
 
         let cpi_accounts = MintTo {
             mint: self.mint_lp.to_account_info(),
-            to: self.user_lp.to_account_info(),
+            to,
             authority: self.config.to_account_info(),
         };
 
         let seeds = &[
             &b"config"[..],
-            &self.config.seed.to_le_bytes(),
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
             &[self.config.config_bump],
         ];
 
@@ -148,4 +223,4 @@ impl <'info> Deposit<'info> {
         Ok(())
     }
 
-}
\ No newline at end of file
+}
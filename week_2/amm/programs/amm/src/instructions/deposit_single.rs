@@ -0,0 +1,188 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked, MintTo, mint_to}
+};
+
+use crate::error::AmmError;
+use crate::state::Config;
+use crate::transfer_fee::amount_after_fee;
+
+
+#[derive(Accounts)]
+pub struct DepositSingle<'info> {
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    // Only the side being deposited, its mint is checked against is_x
+    #[account(
+        mut,
+        token::authority = user,
+    )]
+    pub user_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> DepositSingle<'info> {
+    // The whole of amount_in goes into the pool, see `amm_sim::deposit_single`
+    pub fn deposit_single(&mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_lp_out: u64,
+    ) -> Result<()> {
+
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount_in != 0, AmmError::InvalidAmount);
+        // there is no ratio to zap into before the first two-sided deposit
+        require!(self.mint_lp.supply != 0, AmmError::ZeroBalance);
+
+        let mint_in = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        require_keys_eq!(self.user_in.mint, mint_in.key(), AmmError::InvalidMint);
+
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config.update_oracle(reserve_x, reserve_y)?;
+
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+
+        let received = amount_after_fee(mint_in, amount_in)?;
+        let curve = self.config.curve_at(Clock::get()?.unix_timestamp)?;
+        let zap = amm_sim::deposit_single(
+            curve,
+            reserve_in,
+            reserve_out,
+            self.mint_lp.supply,
+            self.config.fee,
+            received,
+        ).map_err(AmmError::from)?;
+
+        require!(zap.lp >= min_lp_out, AmmError::SlippageExceeded);
+
+        self.deposit_token(is_x, amount_in)?;
+        self.mint_lp_tokens(zap.lp)?;
+
+        self.config.accrue_protocol_fee(is_x, zap.swap.fee)?;
+
+        Ok(())
+    }
+
+    pub fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
+
+        let (mint, decimals, cpi_program, to) = match is_x {
+            true => (
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+                self.vault_x.to_account_info(),
+            ),
+            false => (
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+                self.vault_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: self.user_in.to_account_info(),
+            mint,
+            to,
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(ctx, amount, decimals)?;
+        Ok(())
+    }
+
+    pub fn mint_lp_tokens(&self, amount: u64) -> Result<()> {
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = MintTo {
+            mint: self.mint_lp.to_account_info(),
+            to: self.user_lp.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"config"[..],
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        mint_to(ctx, amount)?;
+
+        Ok(())
+    }
+}
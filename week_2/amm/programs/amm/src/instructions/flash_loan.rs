@@ -0,0 +1,176 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as sysvar_instructions, load_current_index_checked, load_instruction_at_checked
+};
+use anchor_lang::Discriminator;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}
+};
+
+use crate::error::AmmError;
+use crate::instructions::flash_repay::FLASH_REPAY_CONFIG_INDEX;
+use crate::state::Config;
+
+
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        associated_token::mint = mint_x,
+        associated_token::authority = borrower,
+        associated_token::token_program = token_program_x
+    )]
+    pub borrower_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        associated_token::mint = mint_y,
+        associated_token::authority = borrower,
+        associated_token::token_program = token_program_y
+    )]
+    pub borrower_y: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the instructions sysvar, pinned by address
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashLoan<'info> {
+    // Lends `amount` of x (is_x) or y out of the vault. A flash_repay for this
+    // pool has to follow in the same transaction, returning amount plus the pool fee.
+    pub fn flash_loan(&mut self, is_x: bool, amount: u64) -> Result<()> {
+
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount != 0, AmmError::InvalidAmount);
+        require!(get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT, AmmError::FlashLoanCpi);
+
+        // also refuses a second loan while one is open
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let reserve = match is_x {
+            true => reserve_x,
+            false => reserve_y,
+        };
+        require!(amount <= reserve, AmmError::InsufficientBalance);
+
+        self.check_repay_follows()?;
+
+        let fee = amm_sim::flash_loan_fee(amount, self.config.fee).map_err(AmmError::from)?;
+
+        self.config.flash_loan_due = amount.checked_add(fee).ok_or(AmmError::Overflow)?;
+        self.config.flash_loan_is_x = is_x;
+
+        self.withdraw_token(is_x, amount)?;
+
+        Ok(())
+    }
+
+    fn check_repay_follows(&self) -> Result<()> {
+        let instructions = self.instructions.to_account_info();
+        let current = load_current_index_checked(&instructions)? as usize;
+
+        let mut index = current + 1;
+        while let Ok(instruction) = load_instruction_at_checked(index, &instructions) {
+            let is_repay = instruction.program_id == crate::ID
+                && instruction.data.starts_with(crate::instruction::FlashRepay::DISCRIMINATOR)
+                && instruction.accounts.get(FLASH_REPAY_CONFIG_INDEX)
+                    .is_some_and(|meta| meta.pubkey == self.config.key());
+
+            if is_repay {
+                return Ok(());
+            }
+            index += 1;
+        }
+
+        err!(AmmError::MissingFlashRepay)
+    }
+
+    pub fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.borrower_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.borrower_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"config"[..],
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, decimals)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
+
+use crate::error::AmmError;
+use crate::state::Config;
+use crate::transfer_fee::amount_before_fee;
+
+// Position of `config` in the FlashRepay accounts, read by flash_loan when it
+// looks ahead for the repayment
+pub const FLASH_REPAY_CONFIG_INDEX: usize = 3;
+
+
+#[derive(Accounts)]
+pub struct FlashRepay<'info> {
+
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::authority = borrower,
+        token::token_program = token_program_x
+    )]
+    pub borrower_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::authority = borrower,
+        token::token_program = token_program_y
+    )]
+    pub borrower_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashRepay<'info> {
+    pub fn flash_repay(&mut self) -> Result<()> {
+
+        require!(get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT, AmmError::FlashLoanCpi);
+
+        let due = self.config.flash_loan_due;
+        require!(due != 0, AmmError::NoFlashLoan);
+
+        let is_x = self.config.flash_loan_is_x;
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.borrower_x, &self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.borrower_y, &self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+
+        // the vault has to end up with the full amount due, so any transfer fee is on top
+        let amount = amount_before_fee(mint, due)?;
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.borrower.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(ctx, amount, mint.decimals)?;
+
+        // The fee stays in the vault outside the protocol share, so it accrues to LPs
+        self.config.flash_loan_due = 0;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}
+};
+
+use amm_sim::concentrated::check_range;
+use crate::error::AmmError;
+use crate::state::{Config, Position, TickArrays};
+use crate::transfer_fee::amount_before_fee;
+
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct IncreaseLiquidity<'info> {
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [
+            b"position",
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump,
+        space = 8 + Position::INIT_SPACE,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program_x
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program_y
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> IncreaseLiquidity<'info> {
+    // Opens the owner's position over [tick_lower, tick_upper) on first use
+    pub fn open_position(&mut self, tick_lower: i32, tick_upper: i32, bumps: IncreaseLiquidityBumps) -> Result<()> {
+        check_range(tick_lower, tick_upper, self.config.tick_spacing).map_err(AmmError::from)?;
+
+        if self.position.owner == Pubkey::default() {
+            self.position.set_inner(Position {
+                config: self.config.key(),
+                owner: self.owner.key(),
+                tick_lower,
+                tick_upper,
+                liquidity: 0,
+                fee_growth_inside_x: 0,
+                fee_growth_inside_y: 0,
+                tokens_owed_x: 0,
+                tokens_owed_y: 0,
+                bump: bumps.position,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Adds `liquidity` to the position. max_x and max_y cap what leaves the owner.
+    // remaining_accounts are the tick arrays holding the position's two ticks,
+    // one account if both fall in the same array.
+    pub fn increase_liquidity(&mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        liquidity: u128,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<()> {
+
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(liquidity != 0, AmmError::InvalidAmount);
+
+        let (tick_lower, tick_upper) = (self.position.tick_lower, self.position.tick_upper);
+        let mut tick_arrays = TickArrays::load(&self.config, remaining_accounts)?;
+        require!(
+            tick_arrays.holds(tick_lower) && tick_arrays.holds(tick_upper),
+            AmmError::TickArrayMissing
+        );
+
+        self.config.update_concentrated_oracle()?;
+
+        let mut state = self.config.pool_state()?;
+        let mut ticks = tick_arrays.ticks();
+        let mut position = self.position.load();
+
+        let delta = i128::try_from(liquidity).map_err(|_| AmmError::Overflow)?;
+        let amounts = state
+            .modify_position(&mut ticks, &mut position, tick_lower, tick_upper, delta)
+            .map_err(AmmError::from)?;
+
+        // the vaults have to receive the amounts in full, transfer fees come on top
+        let deposit_x = amount_before_fee(&self.mint_x, amounts.x)?;
+        let deposit_y = amount_before_fee(&self.mint_y, amounts.y)?;
+        require!(deposit_x <= max_x && deposit_y <= max_y, AmmError::SlippageExceeded);
+
+        self.config.set_pool_state(&state);
+        tick_arrays.store(&ticks)?;
+        self.position.store(&position);
+
+        // a range entirely on one side of the price only takes one token
+        if deposit_x != 0 {
+            self.deposit_token(true, deposit_x)?;
+        }
+        if deposit_y != 0 {
+            self.deposit_token(false, deposit_y)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
+
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.user_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.user_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.owner.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(ctx, amount, decimals)?;
+        Ok(())
+    }
+}
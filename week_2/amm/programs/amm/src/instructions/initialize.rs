@@ -1,18 +1,35 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount}
+    metadata::{
+        mpl_token_metadata::{instructions::CreateV1CpiBuilder, types::TokenStandard},
+        Metadata, MetadataAccount,
+    },
+    token_interface::{Mint, TokenAccount, TokenInterface}
 };
-use crate::state::Config;
+use crate::error::AmmError;
+use crate::state::{Config, CurveType, MAX_AMP, MAX_FEE, MAX_LP_DECIMALS, MIN_AMP};
 
 
 #[derive(Accounts)]
-#[instruction(seed: u64)]
+#[instruction(fee_tier: u16, lp_decimals: u8)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
-    pub mint_x: Account <'info, Mint>,
-    pub mint_y: Account <'info, Mint>,
+
+    // Sorting the pair makes the config address canonical, so (x, y) and (y, x)
+    // cannot both be opened and `init` rejects a second pool for the same tier.
+    #[account(
+        constraint = mint_x.key() < mint_y.key() @ AmmError::UnsortedMints,
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
 
     #[account(
         init,
@@ -20,61 +37,202 @@ pub struct Initialize<'info> {
         // space = 8 + Config::INIT_SPACE,
         seeds = [b"lp", config.key.as_ref()],
         bump,
-        mint::decimals = 6,
+        mint::decimals = lp_decimals,
         mint::authority = config,
+        mint::token_program = token_program,
 
     )]
-    pub mint_lp: Account <'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
 
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
         payer = initializer,
-        seeds = [b"config", seed.to_le_bytes().as_ref()],
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref(), fee_tier.to_le_bytes().as_ref()],
         bump,
         space = 8 + Config::INIT_SPACE,
     )]
     pub config: Account<'info, Config>,
-    pub token_program: Program<'info, Token>,
+
+    /// CHECK: created below by the token metadata program, which checks the address
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint_lp.key().as_ref()],
+        seeds::program = metadata_program.key(),
+        bump,
+    )]
+    pub metadata_lp: UncheckedAccount<'info>,
+
+    /// CHECK: mint_x's metadata, read for its symbol when it exists
+    #[account(
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint_x.key().as_ref()],
+        seeds::program = metadata_program.key(),
+        bump,
+    )]
+    pub metadata_x: UncheckedAccount<'info>,
+
+    /// CHECK: mint_y's metadata, read for its symbol when it exists
+    #[account(
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint_y.key().as_ref()],
+        seeds::program = metadata_program.key(),
+        bump,
+    )]
+    pub metadata_y: UncheckedAccount<'info>,
+
+    // LP mint program, mint_x and mint_y may each live under either token program
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
+    pub metadata_program: Program<'info, Metadata>,
     pub system_program: Program<'info, System>,
+    /// CHECK: instructions sysvar, required by token metadata's CreateV1
+    #[account(address = sysvar_instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
 }
 
 impl<'info> Initialize<'info> {
     pub fn init(
         &mut self,
-        seed: u64,
-        fee: u16,
+        fee_tier: u16,
+        lp_decimals: u8,
+        curve: CurveType,
+        amp: u64,
         authority: Option<Pubkey>,
         bumps: InitializeBumps) -> Result<()> {
-        
+
+        require!(fee_tier <= MAX_FEE, AmmError::InvalidFee);
+        require!(lp_decimals <= MAX_LP_DECIMALS, AmmError::InvalidPrecision);
+
+        let amp = match curve {
+            CurveType::ConstantProduct => 0,
+            CurveType::StableSwap => {
+                require!((MIN_AMP..=MAX_AMP).contains(&amp), AmmError::InvalidAmp);
+                amp
+            },
+            CurveType::Concentrated => return err!(AmmError::ConcentratedPool),
+        };
+
         self.config.set_inner(Config{
-            seed,
+            fee_tier,
             authority,
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
-            fee,
+            fee: fee_tier,
             locked: false,
 
+            curve,
+            initial_amp: amp,
+            target_amp: amp,
+            ramp_start_ts: 0,
+            ramp_stop_ts: 0,
+
+            tick_spacing: 0,
+            sqrt_price_x64: 0,
+            tick_current: 0,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+
+            price_x_cumulative: 0,
+            price_y_cumulative: 0,
+            last_update_ts: Clock::get()?.unix_timestamp,
+
+            protocol_fee: 0,
+            fee_recipient: authority.unwrap_or(self.initializer.key()),
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+
+            flash_loan_due: 0,
+            flash_loan_is_x: false,
+
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
         });
 
+        self.create_lp_metadata()?;
+
+        Ok(())
+    }
+
+    // Names the LP token after the pair, "USDC-USDT LP", so wallets can show it
+    pub fn create_lp_metadata(&self) -> Result<()> {
+
+        let symbol_x = token_symbol(&self.metadata_x, &self.mint_x.key());
+        let symbol_y = token_symbol(&self.metadata_y, &self.mint_y.key());
+
+        let pair = format!("{}-{}", symbol_x, symbol_y);
+        let symbol = match pair.len() <= MAX_SYMBOL_LENGTH {
+            true => pair.clone(),
+            false => String::from("LP"),
+        };
+
+        let seeds = &[
+            &b"config"[..],
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        // CreateV1 takes the mint's own token program, the LP mint may be Token-2022.
+        // The config has no instruction to sign an update with, so nothing could
+        // change the metadata anyway.
+        CreateV1CpiBuilder::new(&self.metadata_program.to_account_info())
+            .metadata(&self.metadata_lp.to_account_info())
+            .mint(&self.mint_lp.to_account_info(), false)
+            .authority(&self.config.to_account_info())
+            .payer(&self.initializer.to_account_info())
+            .update_authority(&self.config.to_account_info(), true)
+            .system_program(&self.system_program.to_account_info())
+            .sysvar_instructions(&self.sysvar_instructions.to_account_info())
+            .spl_token_program(Some(&self.token_program.to_account_info()))
+            .name(format!("{} LP", pair))
+            .symbol(symbol)
+            .uri(String::new())
+            .seller_fee_basis_points(0)
+            .is_mutable(false)
+            .token_standard(TokenStandard::Fungible)
+            .invoke_signed(signer_seeds)?;
+
         Ok(())
     }
+}
+
+// Token metadata caps symbols at 10 bytes
+const MAX_SYMBOL_LENGTH: usize = 10;
+
+// A mint's Metaplex symbol, or the start of its address when it has none
+fn token_symbol(metadata: &UncheckedAccount, mint: &Pubkey) -> String {
+    let symbol = match metadata.owner == &anchor_spl::metadata::ID && !metadata.data_is_empty() {
+        true => metadata
+            .try_borrow_data()
+            .ok()
+            .and_then(|data| MetadataAccount::try_deserialize(&mut &data[..]).ok())
+            .map(|metadata| metadata.symbol.trim_matches(char::from(0)).trim().to_string()),
+        false => None,
+    };
+
+    symbol
+        .filter(|symbol| !symbol.is_empty())
+        .unwrap_or_else(|| mint.to_string()[..4].to_string())
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface}
+};
+
+use amm_sim::concentrated::PoolState;
+use crate::error::AmmError;
+use crate::state::{Config, CurveType, MAX_FEE, MAX_TICK_SPACING};
+
+
+// Opens a concentrated liquidity pool under the same config address and vaults a
+// constant product or stable pool for the pair and tier would use. There is no
+// LP mint, liquidity is held in `Position` accounts instead, and ticks in tick
+// arrays opened with `initialize_tick_array`.
+#[derive(Accounts)]
+#[instruction(fee_tier: u16)]
+pub struct InitializeConcentrated<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    #[account(
+        constraint = mint_x.key() < mint_y.key() @ AmmError::UnsortedMints,
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref(), fee_tier.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Config::INIT_SPACE,
+    )]
+    pub config: Account<'info, Config>,
+
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeConcentrated<'info> {
+    // sqrt_price_x64 is the opening price of x in y, as a Q64.64 square root
+    pub fn init(
+        &mut self,
+        fee_tier: u16,
+        tick_spacing: u16,
+        sqrt_price_x64: u128,
+        authority: Option<Pubkey>,
+        bumps: InitializeConcentratedBumps) -> Result<()> {
+
+        require!(fee_tier <= MAX_FEE, AmmError::InvalidFee);
+        require!((1..=MAX_TICK_SPACING).contains(&tick_spacing), AmmError::InvalidTick);
+
+        let state = PoolState::new(sqrt_price_x64).map_err(AmmError::from)?;
+
+        self.config.set_inner(Config{
+            fee_tier,
+            authority,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee: fee_tier,
+            locked: false,
+
+            curve: CurveType::Concentrated,
+            initial_amp: 0,
+            target_amp: 0,
+            ramp_start_ts: 0,
+            ramp_stop_ts: 0,
+
+            tick_spacing,
+            sqrt_price_x64: state.sqrt_price,
+            tick_current: state.tick,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+
+            price_x_cumulative: 0,
+            price_y_cumulative: 0,
+            last_update_ts: Clock::get()?.unix_timestamp,
+
+            protocol_fee: 0,
+            fee_recipient: authority.unwrap_or(self.initializer.key()),
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+
+            flash_loan_due: 0,
+            flash_loan_is_x: false,
+
+            config_bump: bumps.config,
+            // no LP mint
+            lp_bump: 0,
+        });
+
+        Ok(())
+    }
+}
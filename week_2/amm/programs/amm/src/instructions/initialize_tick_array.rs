@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::{check_tick_array_start, Config, CurveType, Tick, TickArray, TICK_ARRAY_SIZE};
+
+
+// Opens the tick array starting at `start_index` for a concentrated pool.
+// Anyone can open one, the payer funds its rent.
+#[derive(Accounts)]
+#[instruction(start_index: i32)]
+pub struct InitializeTickArray<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        constraint = config.curve == CurveType::Concentrated @ AmmError::NotConcentrated,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"tick_array", config.key().as_ref(), start_index.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + TickArray::INIT_SPACE,
+    )]
+    pub tick_array: Account<'info, TickArray>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeTickArray<'info> {
+    pub fn init(&mut self, start_index: i32, bumps: InitializeTickArrayBumps) -> Result<()> {
+        check_tick_array_start(start_index, self.config.tick_spacing)?;
+
+        self.tick_array.set_inner(TickArray {
+            config: self.config.key(),
+            start_index,
+            ticks: vec![Tick::default(); TICK_ARRAY_SIZE],
+            bump: bumps.tick_array,
+        });
+
+        Ok(())
+    }
+}
@@ -1,5 +1,31 @@
 pub mod initialize;
 pub mod deposit;
+pub mod deposit_single;
+pub mod swap;
+pub mod route_swap;
+pub mod withdraw;
+pub mod update;
+pub mod collect_protocol_fees;
+pub mod flash_loan;
+pub mod flash_repay;
+pub mod initialize_concentrated;
+pub mod initialize_tick_array;
+pub mod increase_liquidity;
+pub mod decrease_liquidity;
+pub mod swap_concentrated;
 
 pub use initialize::*;
-pub use deposit::*;
\ No newline at end of file
+pub use deposit::*;
+pub use deposit_single::*;
+pub use swap::*;
+pub use route_swap::*;
+pub use withdraw::*;
+pub use update::*;
+pub use collect_protocol_fees::*;
+pub use flash_loan::*;
+pub use flash_repay::*;
+pub use initialize_concentrated::*;
+pub use initialize_tick_array::*;
+pub use increase_liquidity::*;
+pub use decrease_liquidity::*;
+pub use swap_concentrated::*;
\ No newline at end of file
@@ -0,0 +1,185 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{Mint, TokenAccount, TransferChecked, transfer_checked}
+};
+
+use crate::error::AmmError;
+use crate::instructions::swap::quote;
+use crate::state::Config;
+use crate::transfer_fee::amount_after_fee;
+
+// Accounts per hop in remaining_accounts, in this order:
+//   config (mut), mint_in, mint_out, vault_in (mut), vault_out (mut),
+//   user_in (mut), user_out (mut), token_program_in, token_program_out
+// user_in of each hop is user_out of the one before it.
+pub const ACCOUNTS_PER_HOP: usize = 9;
+pub const MAX_HOPS: usize = 4;
+
+
+#[derive(Accounts)]
+pub struct RouteSwap<'info> {
+    pub user: Signer<'info>,
+}
+
+impl<'info> RouteSwap<'info> {
+    pub fn route_swap(&mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+
+        require!(amount_in != 0, AmmError::InvalidAmount);
+        let hops = remaining_accounts.len() / ACCOUNTS_PER_HOP;
+        require!(
+            hops != 0 && hops <= MAX_HOPS && hops * ACCOUNTS_PER_HOP == remaining_accounts.len(),
+            AmmError::InvalidRoute
+        );
+
+        let mut amount = amount_in;
+        let mut previous_out: Option<Pubkey> = None;
+
+        // Each hop is loaded only once the one before it has settled, so a pool
+        // visited twice is read with its updated reserves
+        for accounts in remaining_accounts.chunks(ACCOUNTS_PER_HOP) {
+            let mut hop = Hop::load(accounts, &self.user.key())?;
+
+            if let Some(previous_out) = previous_out {
+                require_keys_eq!(previous_out, hop.user_in.key(), AmmError::InvalidRoute);
+            }
+            previous_out = Some(hop.user_out.key());
+
+            amount = hop.swap(&self.user, amount)?;
+        }
+
+        require!(amount >= min_amount_out, AmmError::SlippageExceeded);
+
+        Ok(())
+    }
+}
+
+struct Hop<'info> {
+    config: Account<'info, Config>,
+    mint_in: InterfaceAccount<'info, Mint>,
+    mint_out: InterfaceAccount<'info, Mint>,
+    vault_in: InterfaceAccount<'info, TokenAccount>,
+    vault_out: InterfaceAccount<'info, TokenAccount>,
+    user_in: InterfaceAccount<'info, TokenAccount>,
+    user_out: InterfaceAccount<'info, TokenAccount>,
+    token_program_in: &'info AccountInfo<'info>,
+    token_program_out: &'info AccountInfo<'info>,
+    is_x: bool,
+}
+
+impl<'info> Hop<'info> {
+    // Performs by hand the checks `Swap` gets from its account constraints
+    fn load(accounts: &'info [AccountInfo<'info>], user: &Pubkey) -> Result<Self> {
+        let config = Account::<Config>::try_from(&accounts[0])?;
+        let mint_in = InterfaceAccount::<Mint>::try_from(&accounts[1])?;
+        let mint_out = InterfaceAccount::<Mint>::try_from(&accounts[2])?;
+        let vault_in = InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?;
+        let vault_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[4])?;
+        let user_in = InterfaceAccount::<TokenAccount>::try_from(&accounts[5])?;
+        let user_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[6])?;
+        let token_program_in = &accounts[7];
+        let token_program_out = &accounts[8];
+
+        let is_x = match (mint_in.key(), mint_out.key()) {
+            (mint_in, mint_out) if mint_in == config.mint_x && mint_out == config.mint_y => true,
+            (mint_in, mint_out) if mint_in == config.mint_y && mint_out == config.mint_x => false,
+            _ => return err!(AmmError::InvalidRoute),
+        };
+
+        require_keys_eq!(*mint_in.to_account_info().owner, token_program_in.key(), AmmError::InvalidRoute);
+        require_keys_eq!(*mint_out.to_account_info().owner, token_program_out.key(), AmmError::InvalidRoute);
+
+        require_keys_eq!(
+            get_associated_token_address_with_program_id(&config.key(), &mint_in.key(), &token_program_in.key()),
+            vault_in.key(),
+            AmmError::InvalidRoute
+        );
+        require_keys_eq!(
+            get_associated_token_address_with_program_id(&config.key(), &mint_out.key(), &token_program_out.key()),
+            vault_out.key(),
+            AmmError::InvalidRoute
+        );
+
+        require!(user_in.owner == *user && user_in.mint == mint_in.key(), AmmError::InvalidRoute);
+        require!(user_out.owner == *user && user_out.mint == mint_out.key(), AmmError::InvalidRoute);
+
+        Ok(Self {
+            config,
+            mint_in,
+            mint_out,
+            vault_in,
+            vault_out,
+            user_in,
+            user_out,
+            token_program_in,
+            token_program_out,
+            is_x,
+        })
+    }
+
+    // Same steps as `Swap::swap`, returning what lands in user_out
+    fn swap(&mut self, user: &Signer<'info>, amount_in: u64) -> Result<u64> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+
+        let (reserve_x, reserve_y) = match self.is_x {
+            true => self.config.reserves(self.vault_in.amount, self.vault_out.amount)?,
+            false => self.config.reserves(self.vault_out.amount, self.vault_in.amount)?,
+        };
+        self.config.update_oracle(reserve_x, reserve_y)?;
+
+        let received_in = amount_after_fee(&self.mint_in, amount_in)?;
+        let result = quote(&self.config, reserve_x, reserve_y, self.is_x, received_in)?;
+
+        self.deposit_token(user, amount_in)?;
+        self.withdraw_token(result.withdraw)?;
+
+        self.config.accrue_protocol_fee(self.is_x, result.fee)?;
+        self.config.exit(&crate::ID)?;
+
+        amount_after_fee(&self.mint_out, result.withdraw)
+    }
+
+    fn deposit_token(&self, user: &Signer<'info>, amount: u64) -> Result<()> {
+
+        let cpi_accounts = TransferChecked {
+            from: self.user_in.to_account_info(),
+            mint: self.mint_in.to_account_info(),
+            to: self.vault_in.to_account_info(),
+            authority: user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.token_program_in.clone(), cpi_accounts);
+
+        transfer_checked(ctx, amount, self.mint_in.decimals)?;
+        Ok(())
+    }
+
+    fn withdraw_token(&self, amount: u64) -> Result<()> {
+
+        let cpi_accounts = TransferChecked {
+            from: self.vault_out.to_account_info(),
+            mint: self.mint_out.to_account_info(),
+            to: self.user_out.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"config"[..],
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(self.token_program_out.clone(), cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, self.mint_out.decimals)?;
+        Ok(())
+    }
+}
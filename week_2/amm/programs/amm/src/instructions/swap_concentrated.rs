@@ -0,0 +1,198 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}
+};
+
+use crate::error::AmmError;
+use crate::state::{Config, TickArrays};
+use crate::transfer_fee::amount_after_fee;
+
+
+#[derive(Accounts)]
+pub struct SwapConcentrated<'info> {
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_x,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_y,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> SwapConcentrated<'info> {
+    // is_x: the user pays in x and receives y. remaining_accounts are consecutive
+    // tick arrays, including the one holding the current tick and reaching as far
+    // as the swap may move the price. It fails rather than run past the last one.
+    pub fn swap(&mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount_in != 0, AmmError::InvalidAmount);
+
+        let mut tick_arrays = TickArrays::load(&self.config, remaining_accounts)?;
+        let range = tick_arrays.range()?;
+
+        self.config.update_concentrated_oracle()?;
+
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
+        };
+
+        // Only what reaches the vault is priced, a transfer fee on the way in is the user's cost
+        let received_in = amount_after_fee(mint_in, amount_in)?;
+
+        let mut state = self.config.pool_state()?;
+        let mut ticks = tick_arrays.ticks();
+        let result = state
+            .swap(&mut ticks, range, is_x, received_in, self.config.fee, self.config.protocol_fee)
+            .map_err(AmmError::from)?;
+
+        // and min_amount_out is checked against what the user ends up holding
+        let received_out = amount_after_fee(mint_out, result.swap.withdraw)?;
+        require!(received_out >= min_amount_out, AmmError::SlippageExceeded);
+
+        self.config.set_pool_state(&state);
+        tick_arrays.store(&ticks)?;
+        // the LP share of the fee went to fee growth, the rest stays out of it
+        self.config.add_protocol_fee(is_x, result.protocol_fee)?;
+
+        self.deposit_token(is_x, amount_in)?;
+        self.withdraw_token(!is_x, result.swap.withdraw)?;
+
+        Ok(())
+    }
+
+    pub fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
+
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.user_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.user_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(ctx, amount, decimals)?;
+        Ok(())
+    }
+
+    pub fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+
+        let (from, to, mint, decimals, cpi_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.user_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.user_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"config"[..],
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &self.config.fee_tier.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, decimals)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::{
+    Config, CurveType, MAX_AMP, MAX_AMP_CHANGE, MAX_FEE, MAX_PROTOCOL_FEE, MIN_AMP, MIN_RAMP_DURATION
+};
+
+
+#[derive(Accounts)]
+pub struct Update<'info> {
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref(), config.fee_tier.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> Update<'info> {
+    pub fn lock(&mut self) -> Result<()> {
+        self.check_authority()?;
+        self.config.locked = true;
+        Ok(())
+    }
+
+    pub fn unlock(&mut self) -> Result<()> {
+        self.check_authority()?;
+        self.config.locked = false;
+        Ok(())
+    }
+
+    pub fn update_fee(&mut self, fee: u16) -> Result<()> {
+        self.check_authority()?;
+        require!(fee <= MAX_FEE, AmmError::InvalidFee);
+        self.config.fee = fee;
+        Ok(())
+    }
+
+    pub fn set_protocol_fee(&mut self, protocol_fee: u16, fee_recipient: Pubkey) -> Result<()> {
+        self.check_authority()?;
+        require!(protocol_fee <= MAX_PROTOCOL_FEE, AmmError::InvalidFee);
+        self.config.protocol_fee = protocol_fee;
+        self.config.fee_recipient = fee_recipient;
+        Ok(())
+    }
+
+    // Moves the amplification linearly from its current value to target_amp by ramp_stop_ts,
+    // so LPs are never repriced in a single step
+    pub fn ramp_amp(&mut self, target_amp: u64, ramp_stop_ts: i64) -> Result<()> {
+        self.check_authority()?;
+        require!(self.config.curve == CurveType::StableSwap, AmmError::NotStableSwap);
+        require!((MIN_AMP..=MAX_AMP).contains(&target_amp), AmmError::InvalidAmp);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(ramp_stop_ts >= now.saturating_add(MIN_RAMP_DURATION), AmmError::InvalidRamp);
+
+        let current_amp = self.config.amp(now)?;
+        require!(
+            target_amp <= current_amp.saturating_mul(MAX_AMP_CHANGE)
+                && target_amp.saturating_mul(MAX_AMP_CHANGE) >= current_amp,
+            AmmError::InvalidRamp
+        );
+
+        self.config.initial_amp = current_amp;
+        self.config.target_amp = target_amp;
+        self.config.ramp_start_ts = now;
+        self.config.ramp_stop_ts = ramp_stop_ts;
+        Ok(())
+    }
+
+    // Freezes the amplification wherever the ramp currently is
+    pub fn stop_ramp_amp(&mut self) -> Result<()> {
+        self.check_authority()?;
+        require!(self.config.curve == CurveType::StableSwap, AmmError::NotStableSwap);
+
+        let now = Clock::get()?.unix_timestamp;
+        let current_amp = self.config.amp(now)?;
+
+        self.config.initial_amp = current_amp;
+        self.config.target_amp = current_amp;
+        self.config.ramp_start_ts = now;
+        self.config.ramp_stop_ts = now;
+        Ok(())
+    }
+
+    pub fn set_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        self.check_authority()?;
+        self.config.authority = Some(new_authority);
+        Ok(())
+    }
+
+    // Leaves the pool permanently without admin controls, in whatever lock state it is in
+    pub fn renounce_authority(&mut self) -> Result<()> {
+        self.check_authority()?;
+        self.config.authority = None;
+        Ok(())
+    }
+
+    fn check_authority(&self) -> Result<()> {
+        match self.config.authority {
+            Some(authority) => {
+                require_keys_eq!(authority, self.authority.key(), AmmError::InvalidAuthority);
+                Ok(())
+            },
+            None => err!(AmmError::NoAuthoritySet),
+        }
+    }
+}
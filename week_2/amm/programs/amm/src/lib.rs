@@ -1,8 +1,11 @@
 #![allow(unexpected_cfgs)]
 use anchor_lang::prelude::*;
 
+mod error;
 mod instructions;
-mod state;
+pub mod oracle;
+pub mod state;
+mod transfer_fee;
 
 use instructions::*;
 
@@ -12,8 +15,8 @@ declare_id!("8naPSfRLySpWUUVzrksg8ciwo84eVns3WscuAayKkCmf");
 pub mod amm {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, seed: u64, fee: u16, authority: Option<Pubkey>) -> Result<()> {
-        ctx.accounts.init(seed, fee, authority, ctx.bumps)?;
+    pub fn initialize(ctx: Context<Initialize>, fee_tier: u16, lp_decimals: u8, curve: state::CurveType, amp: u64, authority: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.init(fee_tier, lp_decimals, curve, amp, authority, ctx.bumps)?;
         Ok(())
     }
 
@@ -21,5 +24,106 @@ pub mod amm {
         ctx.accounts.deposit(amount, max_x, max_y)?;
         Ok(())
     }
+
+    pub fn deposit_single(ctx: Context<DepositSingle>, is_x: bool, amount_in: u64, min_lp_out: u64) -> Result<()> {
+        ctx.accounts.deposit_single(is_x, amount_in, min_lp_out)?;
+        Ok(())
+    }
+
+    pub fn swap(ctx: Context<Swap>, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        ctx.accounts.swap(is_x, amount_in, min_amount_out)?;
+        Ok(())
+    }
+
+    pub fn route_swap<'info>(ctx: Context<'_, '_, 'info, 'info, RouteSwap<'info>>, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        ctx.accounts.route_swap(ctx.remaining_accounts, amount_in, min_amount_out)?;
+        Ok(())
+    }
+
+    pub fn flash_loan(ctx: Context<FlashLoan>, is_x: bool, amount: u64) -> Result<()> {
+        ctx.accounts.flash_loan(is_x, amount)?;
+        Ok(())
+    }
+
+    pub fn flash_repay(ctx: Context<FlashRepay>) -> Result<()> {
+        ctx.accounts.flash_repay()?;
+        Ok(())
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, lp_amount: u64, min_x: u64, min_y: u64) -> Result<()> {
+        ctx.accounts.withdraw(lp_amount, min_x, min_y)?;
+        Ok(())
+    }
+
+    pub fn initialize_concentrated(ctx: Context<InitializeConcentrated>, fee_tier: u16, tick_spacing: u16, sqrt_price_x64: u128, authority: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.init(fee_tier, tick_spacing, sqrt_price_x64, authority, ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn initialize_tick_array(ctx: Context<InitializeTickArray>, start_index: i32) -> Result<()> {
+        ctx.accounts.init(start_index, ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn increase_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, IncreaseLiquidity<'info>>, tick_lower: i32, tick_upper: i32, liquidity: u128, max_x: u64, max_y: u64) -> Result<()> {
+        ctx.accounts.open_position(tick_lower, tick_upper, ctx.bumps)?;
+        ctx.accounts.increase_liquidity(ctx.remaining_accounts, liquidity, max_x, max_y)?;
+        Ok(())
+    }
+
+    pub fn decrease_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, DecreaseLiquidity<'info>>, liquidity: u128, min_x: u64, min_y: u64) -> Result<()> {
+        ctx.accounts.decrease_liquidity(ctx.remaining_accounts, liquidity, min_x, min_y)?;
+        Ok(())
+    }
+
+    pub fn swap_concentrated<'info>(ctx: Context<'_, '_, 'info, 'info, SwapConcentrated<'info>>, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        ctx.accounts.swap(ctx.remaining_accounts, is_x, amount_in, min_amount_out)?;
+        Ok(())
+    }
+
+    pub fn lock(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.lock()?;
+        Ok(())
+    }
+
+    pub fn unlock(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.unlock()?;
+        Ok(())
+    }
+
+    pub fn update_fee(ctx: Context<Update>, fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee)?;
+        Ok(())
+    }
+
+    pub fn set_protocol_fee(ctx: Context<Update>, protocol_fee: u16, fee_recipient: Pubkey) -> Result<()> {
+        ctx.accounts.set_protocol_fee(protocol_fee, fee_recipient)?;
+        Ok(())
+    }
+
+    pub fn ramp_amp(ctx: Context<Update>, target_amp: u64, ramp_stop_ts: i64) -> Result<()> {
+        ctx.accounts.ramp_amp(target_amp, ramp_stop_ts)?;
+        Ok(())
+    }
+
+    pub fn stop_ramp_amp(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.stop_ramp_amp()?;
+        Ok(())
+    }
+
+    pub fn set_authority(ctx: Context<Update>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.set_authority(new_authority)?;
+        Ok(())
+    }
+
+    pub fn renounce_authority(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.renounce_authority()?;
+        Ok(())
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect_protocol_fees()?;
+        Ok(())
+    }
 }
 
@@ -0,0 +1,189 @@
+use anchor_lang::prelude::*;
+use amm_sim::{concentrated, StableSwap};
+
+use crate::error::AmmError;
+use crate::state::{Config, CurveType};
+
+// Prices are Q64.64 fixed point: the real price is the value divided by 2^64.
+//
+// Config keeps a running sum of price × seconds for both directions, updated
+// with the pre-trade reserves before every swap, deposit and withdraw. A price
+// only counts for as long as it stood, so moving it for one transaction buys
+// almost no weight. To read a TWAP, take an `observe` at the start of the
+// window, another at the end, and call `twap` on the pair.
+
+const Q64: u128 = 1 << 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price_x_cumulative: u128,
+    pub price_y_cumulative: u128,
+}
+
+impl Observation {
+    // Average prices of x in y and of y in x between `earlier` and self
+    pub fn twap(&self, earlier: &Observation) -> Result<(u128, u128)> {
+        require!(self.timestamp > earlier.timestamp, AmmError::InvalidAmount);
+        let elapsed = (self.timestamp - earlier.timestamp) as u128;
+
+        // the accumulators wrap, their difference stays exact
+        Ok((
+            self.price_x_cumulative.wrapping_sub(earlier.price_x_cumulative) / elapsed,
+            self.price_y_cumulative.wrapping_sub(earlier.price_y_cumulative) / elapsed,
+        ))
+    }
+}
+
+// Accumulators as they would read at `now`, without writing to the pool.
+// reserve_x and reserve_y are `Config::reserves` of the pool's vaults, a
+// concentrated pool prices off its sqrt price and ignores them.
+//
+// Every trade, deposit and withdraw updates the oracle first, so a price that
+// can't be worked out must not fail them. The observation is skipped instead,
+// keeping the last timestamp, and the time since counts towards the next price
+// that can be.
+pub fn observe(config: &Config, reserve_x: u64, reserve_y: u64, now: i64) -> Observation {
+    let mut observation = Observation {
+        timestamp: now,
+        price_x_cumulative: config.price_x_cumulative,
+        price_y_cumulative: config.price_y_cumulative,
+    };
+
+    let elapsed = now.saturating_sub(config.last_update_ts);
+    let priced = config.curve == CurveType::Concentrated || (reserve_x != 0 && reserve_y != 0);
+    if elapsed > 0 && priced {
+        match spot_price(config, reserve_x, reserve_y, now) {
+            Ok((price_x, price_y)) => {
+                observation.price_x_cumulative = observation.price_x_cumulative
+                    .wrapping_add(price_x.wrapping_mul(elapsed as u128));
+                observation.price_y_cumulative = observation.price_y_cumulative
+                    .wrapping_add(price_y.wrapping_mul(elapsed as u128));
+            },
+            Err(_) => observation.timestamp = config.last_update_ts,
+        }
+    }
+
+    observation
+}
+
+// Marginal price of x in y and of y in x for the pool's curve
+pub fn spot_price(config: &Config, reserve_x: u64, reserve_y: u64, now: i64) -> Result<(u128, u128)> {
+    let priced = config.curve == CurveType::Concentrated || (reserve_x != 0 && reserve_y != 0);
+    require!(priced, AmmError::ZeroBalance);
+
+    let (numerator, denominator) = match config.curve {
+        CurveType::ConstantProduct => (reserve_y as u128, reserve_x as u128),
+        CurveType::StableSwap => {
+            // dy/dx of the invariant, ∂F/∂x ÷ ∂F/∂y scaled through by x·y:
+            //   (Ann·x·y + y·D³/4xy) / (Ann·x·y + x·D³/4xy)
+            // Both reserves are brought under 2^48 first, which keeps the ratio
+            // and every product below in range.
+            let shift = (64 - reserve_x.max(reserve_y).leading_zeros()).saturating_sub(48);
+            let (x, y) = ((reserve_x >> shift).max(1), (reserve_y >> shift).max(1));
+
+            let amp = config.amp(now)?;
+            let ann = (amp as u128) * 4;
+            let d = StableSwap::new(amp).compute_d(x, y).map_err(AmmError::from)?;
+            let (x, y) = (x as u128, y as u128);
+            let d_p = (d.saturating_mul(d) / (x * 2)).saturating_mul(d) / (y * 2);
+
+            let base = ann.saturating_mul(x).saturating_mul(y);
+            (
+                base.saturating_add(d_p.saturating_mul(y)),
+                base.saturating_add(d_p.saturating_mul(x)),
+            )
+        },
+        CurveType::Concentrated => {
+            // saturates past a price of 2^64, the top of the tick range
+            let price_x = concentrated::price_from_sqrt_price(config.sqrt_price_x64).unwrap_or(u128::MAX);
+            return Ok((price_x, q64_ratio(Q64, price_x)));
+        },
+    };
+
+    Ok((q64_ratio(numerator, denominator), q64_ratio(denominator, numerator)))
+}
+
+// numerator / denominator as Q64.64, saturating
+fn q64_ratio(mut numerator: u128, mut denominator: u128) -> u128 {
+    // with the denominator under 2^64 the remainder can be shifted up exactly
+    while denominator >= Q64 {
+        numerator >>= 1;
+        denominator >>= 1;
+    }
+
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    quotient.saturating_mul(Q64).saturating_add((remainder << 64) / denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(curve: CurveType, amp: u64) -> Config {
+        Config {
+            fee_tier: 30,
+            authority: None,
+            mint_x: Pubkey::new_unique(),
+            mint_y: Pubkey::new_unique(),
+            fee: 30,
+            locked: false,
+            curve,
+            initial_amp: amp,
+            target_amp: amp,
+            ramp_start_ts: 0,
+            ramp_stop_ts: 0,
+            tick_spacing: 0,
+            sqrt_price_x64: 0,
+            tick_current: 0,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+            price_x_cumulative: 7,
+            price_y_cumulative: 9,
+            last_update_ts: 100,
+            protocol_fee: 0,
+            fee_recipient: Pubkey::new_unique(),
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            flash_loan_due: 0,
+            flash_loan_is_x: false,
+            config_bump: 255,
+            lp_bump: 255,
+        }
+    }
+
+    #[test]
+    fn prices_accumulate_for_as_long_as_they_stood() {
+        let config = config(CurveType::ConstantProduct, 0);
+        let observation = observe(&config, 1_000, 2_000, 110);
+
+        assert_eq!(observation.timestamp, 110);
+        assert_eq!(observation.price_x_cumulative, 7 + 2 * Q64 * 10);
+        assert_eq!(observation.price_y_cumulative, 9 + Q64 / 2 * 10);
+        assert_eq!(observation.twap(&observe(&config, 1_000, 2_000, 100)).unwrap(), (2 * Q64, Q64 / 2));
+    }
+
+    #[test]
+    fn a_price_that_cannot_be_worked_out_is_skipped() {
+        // no amplification to price a stable pool with
+        let config = config(CurveType::StableSwap, 0);
+        assert!(spot_price(&config, 1_000, 2_000, 110).is_err());
+
+        let observation = observe(&config, 1_000, 2_000, 110);
+        assert_eq!(
+            observation,
+            Observation { timestamp: 100, price_x_cumulative: 7, price_y_cumulative: 9 }
+        );
+    }
+
+    #[test]
+    fn a_lopsided_stable_pool_is_still_priced() {
+        let config = config(CurveType::StableSwap, 4_520);
+        let (price_x, price_y) = spot_price(&config, 14_248_108, 4, 110).unwrap();
+
+        // x is nearly worthless against the last few units of y
+        assert!(price_x < Q64 / 1_000 && price_y > 1_000 * Q64);
+    }
+}
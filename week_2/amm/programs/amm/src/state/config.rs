@@ -1,17 +1,165 @@
 use anchor_lang::prelude::*;
+use amm_sim::{concentrated::PoolState, Curve};
+
+use crate::error::AmmError;
+use crate::oracle;
+
+// Highest swap fee an authority can set, in basis points
+pub const MAX_FEE: u16 = 1_000;
+// Highest share of the swap fee that can go to the protocol, in basis points of the fee
+pub const MAX_PROTOCOL_FEE: u16 = 5_000;
+// Upper bound on the LP mint decimals chosen at init
+pub const MAX_LP_DECIMALS: u8 = 9;
+// Bounds on the StableSwap amplification coefficient
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+// A ramp can move the amplification at most this factor either way, over at least a day
+pub const MAX_AMP_CHANGE: u64 = 10;
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+// Widest tick spacing a concentrated pool can be opened with
+pub const MAX_TICK_SPACING: u16 = 16_384;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    ConstantProduct,
+    StableSwap,
+    Concentrated,
+}
 
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
 
-    pub seed: u64,
+    // Pools are keyed by (mint_x, mint_y, fee_tier) with mint_x < mint_y, one per pair and tier.
+    // The tier is the fee the pool was opened with, the live fee can still be updated.
+    pub fee_tier: u16,
     pub authority: Option<Pubkey>,
     pub mint_x: Pubkey,
     pub mint_y: Pubkey,
     pub fee: u16,
     pub locked: bool,
 
+    pub curve: CurveType,
+    // StableSwap amplification, moving linearly from initial_amp to target_amp
+    // between the ramp timestamps. Unused by constant product pools.
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub ramp_start_ts: i64,
+    pub ramp_stop_ts: i64,
+
+    // Concentrated liquidity state, see `amm_sim::concentrated`: the Q64.64 sqrt
+    // price, the tick at or below it, the liquidity in range there and the fee
+    // growth per unit of liquidity. Unused by the other curves.
+    pub tick_spacing: u16,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_x: u128,
+    pub fee_growth_global_y: u128,
+
+    // TWAP accumulators, see `oracle`
+    pub price_x_cumulative: u128,
+    pub price_y_cumulative: u128,
+    pub last_update_ts: i64,
+
+    pub protocol_fee: u16,
+    pub fee_recipient: Pubkey,
+    // Protocol fees sitting in the vaults until collected, not part of the reserves
+    pub protocol_fees_x: u64,
+    pub protocol_fees_y: u64,
+
+    // Principal plus fee owed by an open flash loan, zero when none is open
+    pub flash_loan_due: u64,
+    pub flash_loan_is_x: bool,
+
     pub config_bump: u8,
     pub lp_bump: u8,
 
-}
\ No newline at end of file
+}
+
+impl Config {
+    pub fn amp(&self, now: i64) -> Result<u64> {
+        if now >= self.ramp_stop_ts || self.ramp_stop_ts <= self.ramp_start_ts {
+            return Ok(self.target_amp);
+        }
+
+        let elapsed = now.saturating_sub(self.ramp_start_ts).max(0) as u128;
+        let duration = (self.ramp_stop_ts - self.ramp_start_ts) as u128;
+        let (initial, target) = (self.initial_amp as u128, self.target_amp as u128);
+
+        let amp = match target >= initial {
+            true => initial + (target - initial) * elapsed / duration,
+            false => initial - (initial - target) * elapsed / duration,
+        };
+        u64::try_from(amp).map_err(|_| error!(AmmError::Overflow))
+    }
+
+    // The curve the pool prices with at `now`, amp included
+    pub fn curve_at(&self, now: i64) -> Result<Curve> {
+        Ok(match self.curve {
+            CurveType::ConstantProduct => Curve::ConstantProduct,
+            CurveType::StableSwap => Curve::StableSwap { amp: self.amp(now)? },
+            CurveType::Concentrated => return err!(AmmError::ConcentratedPool),
+        })
+    }
+
+    pub fn pool_state(&self) -> Result<PoolState> {
+        require!(self.curve == CurveType::Concentrated, AmmError::NotConcentrated);
+        Ok(PoolState {
+            sqrt_price: self.sqrt_price_x64,
+            tick: self.tick_current,
+            liquidity: self.liquidity,
+            fee_growth_global_x: self.fee_growth_global_x,
+            fee_growth_global_y: self.fee_growth_global_y,
+        })
+    }
+
+    pub fn set_pool_state(&mut self, state: &PoolState) {
+        self.sqrt_price_x64 = state.sqrt_price;
+        self.tick_current = state.tick;
+        self.liquidity = state.liquidity;
+        self.fee_growth_global_x = state.fee_growth_global_x;
+        self.fee_growth_global_y = state.fee_growth_global_y;
+    }
+
+    // Call with the reserves as they stood before the instruction moves them
+    pub fn update_oracle(&mut self, reserve_x: u64, reserve_y: u64) -> Result<()> {
+        let observation = oracle::observe(self, reserve_x, reserve_y, Clock::get()?.unix_timestamp);
+
+        self.price_x_cumulative = observation.price_x_cumulative;
+        self.price_y_cumulative = observation.price_y_cumulative;
+        self.last_update_ts = observation.timestamp;
+        Ok(())
+    }
+
+    // A concentrated pool prices off its sqrt price, it has no reserves to pass
+    pub fn update_concentrated_oracle(&mut self) -> Result<()> {
+        require!(self.curve == CurveType::Concentrated, AmmError::NotConcentrated);
+        self.update_oracle(0, 0)
+    }
+
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        // a concentrated pool's vaults hold its positions, not one pair of reserves
+        require!(self.curve != CurveType::Concentrated, AmmError::ConcentratedPool);
+        // vault balances mean nothing while a loan is out, nothing may price off them
+        require!(self.flash_loan_due == 0, AmmError::FlashLoanActive);
+        let x = vault_x.checked_sub(self.protocol_fees_x).ok_or(AmmError::Underflow)?;
+        let y = vault_y.checked_sub(self.protocol_fees_y).ok_or(AmmError::Underflow)?;
+        Ok((x, y))
+    }
+
+    pub fn accrue_protocol_fee(&mut self, is_x: bool, swap_fee: u64) -> Result<()> {
+        let protocol_fee = amm_sim::protocol_fee(swap_fee, self.protocol_fee).map_err(AmmError::from)?;
+        self.add_protocol_fee(is_x, protocol_fee)
+    }
+
+    pub fn add_protocol_fee(&mut self, is_x: bool, protocol_fee: u64) -> Result<()> {
+        let accrued = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *accrued = accrued.checked_add(protocol_fee).ok_or(AmmError::Overflow)?;
+
+        Ok(())
+    }
+}
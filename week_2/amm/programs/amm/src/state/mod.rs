@@ -1,3 +1,7 @@
 pub mod config;
+pub mod position;
+pub mod ticks;
 
-pub use config::*;
\ No newline at end of file
+pub use config::*;
+pub use position::*;
+pub use ticks::*;
\ No newline at end of file
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+use amm_sim::concentrated;
+
+// Liquidity an owner holds over one tick range of a concentrated pool, one
+// account per (config, owner, tick_lower, tick_upper)
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    // Fee growth inside the range when the position was last touched
+    pub fee_growth_inside_x: u128,
+    pub fee_growth_inside_y: u128,
+    // Fees and withdrawn liquidity not yet paid out
+    pub tokens_owed_x: u64,
+    pub tokens_owed_y: u64,
+    pub bump: u8,
+}
+
+impl Position {
+    pub fn load(&self) -> concentrated::Position {
+        concentrated::Position {
+            liquidity: self.liquidity,
+            fee_growth_inside_x: self.fee_growth_inside_x,
+            fee_growth_inside_y: self.fee_growth_inside_y,
+            tokens_owed_x: self.tokens_owed_x,
+            tokens_owed_y: self.tokens_owed_y,
+        }
+    }
+
+    pub fn store(&mut self, position: &concentrated::Position) {
+        self.liquidity = position.liquidity;
+        self.fee_growth_inside_x = position.fee_growth_inside_x;
+        self.fee_growth_inside_y = position.fee_growth_inside_y;
+        self.tokens_owed_x = position.tokens_owed_x;
+        self.tokens_owed_y = position.tokens_owed_y;
+    }
+}
@@ -0,0 +1,169 @@
+use core::ops::RangeInclusive;
+
+use anchor_lang::prelude::*;
+use amm_sim::concentrated::{self, MAX_TICK, MIN_TICK};
+
+use crate::error::AmmError;
+use crate::state::Config;
+
+// A concentrated pool's ticks live in tick arrays, one account per run of
+// TICK_ARRAY_SIZE spaced ticks, at [b"tick_array", config, start_index]. A
+// position only touches the arrays holding its two ticks and a swap the run of
+// arrays it moves the price across, so how many ticks a pool can have
+// initialized is not capped by any one account.
+pub const TICK_ARRAY_SIZE: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Tick {
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+    pub fee_growth_outside_x: u128,
+    pub fee_growth_outside_y: u128,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct TickArray {
+    pub config: Pubkey,
+    // First tick of the array, a multiple of the array span
+    pub start_index: i32,
+    // Slot i holds tick start_index + i × tick_spacing, uninitialized while its
+    // gross liquidity is zero. Always TICK_ARRAY_SIZE long.
+    #[max_len(TICK_ARRAY_SIZE)]
+    pub ticks: Vec<Tick>,
+    pub bump: u8,
+}
+
+// Ticks one array covers
+pub fn tick_array_span(tick_spacing: u16) -> i32 {
+    tick_spacing as i32 * TICK_ARRAY_SIZE as i32
+}
+
+// Start index of the array holding `tick`
+pub fn tick_array_start(tick: i32, tick_spacing: u16) -> i32 {
+    let span = tick_array_span(tick_spacing);
+    tick.div_euclid(span) * span
+}
+
+// A valid start index is aligned to the span and covers part of the tick range
+pub fn check_tick_array_start(start_index: i32, tick_spacing: u16) -> Result<()> {
+    let valid = tick_array_start(start_index, tick_spacing) == start_index
+        && start_index <= MAX_TICK
+        && start_index + tick_array_span(tick_spacing) > MIN_TICK;
+    require!(valid, AmmError::InvalidTick);
+    Ok(())
+}
+
+// The tick arrays an instruction was passed in remaining_accounts, sorted by
+// start index. Their ticks are read into one list for `amm_sim::concentrated`
+// and written back by `store`.
+pub struct TickArrays<'info> {
+    tick_spacing: u16,
+    arrays: Vec<Account<'info, TickArray>>,
+}
+
+impl<'info> TickArrays<'info> {
+    pub fn load(config: &Account<'info, Config>, accounts: &'info [AccountInfo<'info>]) -> Result<Self> {
+        require!(!accounts.is_empty(), AmmError::TickArrayMissing);
+
+        let mut arrays = accounts
+            .iter()
+            .map(Account::<TickArray>::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        arrays.sort_by_key(|array| array.start_index);
+
+        for array in arrays.iter() {
+            require_keys_eq!(array.config, config.key(), AmmError::InvalidTickArray);
+        }
+        // one copy of each array, or the last one written back would win
+        require!(
+            arrays.windows(2).all(|pair| pair[0].start_index < pair[1].start_index),
+            AmmError::InvalidTickArray
+        );
+
+        Ok(Self { tick_spacing: config.tick_spacing, arrays })
+    }
+
+    // Whether the array holding `tick` was passed
+    pub fn holds(&self, tick: i32) -> bool {
+        let start = tick_array_start(tick, self.tick_spacing);
+        self.arrays.iter().any(|array| array.start_index == start)
+    }
+
+    // Ticks the arrays cover with no gap between them, what a swap can run across
+    pub fn range(&self) -> Result<RangeInclusive<i32>> {
+        let span = tick_array_span(self.tick_spacing);
+        require!(
+            self.arrays.windows(2).all(|pair| pair[1].start_index == pair[0].start_index + span),
+            AmmError::InvalidTickArray
+        );
+
+        let (first, last) = match (self.arrays.first(), self.arrays.last()) {
+            (Some(first), Some(last)) => (first.start_index, last.start_index),
+            _ => return err!(AmmError::TickArrayMissing),
+        };
+        Ok(first.max(MIN_TICK)..=(last + span - 1).min(MAX_TICK))
+    }
+
+    // Initialized ticks of every array, sorted by index
+    pub fn ticks(&self) -> Vec<concentrated::Tick> {
+        let spacing = self.tick_spacing as i32;
+        self.arrays
+            .iter()
+            .flat_map(|array| {
+                array.ticks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tick)| tick.liquidity_gross != 0)
+                    .map(move |(slot, tick)| tick.load(array.start_index + slot as i32 * spacing))
+            })
+            .collect()
+    }
+
+    // Writes `ticks` back over the arrays' slots and persists the arrays. A tick
+    // missing from the list, dropped at zero liquidity, is cleared.
+    pub fn store(&mut self, ticks: &[concentrated::Tick]) -> Result<()> {
+        let spacing = self.tick_spacing as i32;
+        for array in self.arrays.iter_mut() {
+            array.ticks.fill(Tick::default());
+        }
+
+        for tick in ticks {
+            let start = tick_array_start(tick.index, self.tick_spacing);
+            let array = self
+                .arrays
+                .iter_mut()
+                .find(|array| array.start_index == start)
+                .ok_or(AmmError::TickArrayMissing)?;
+            array.ticks[((tick.index - start) / spacing) as usize] = Tick::from(*tick);
+        }
+
+        for array in self.arrays.iter() {
+            array.exit(&crate::ID)?;
+        }
+        Ok(())
+    }
+}
+
+impl Tick {
+    fn load(&self, index: i32) -> concentrated::Tick {
+        concentrated::Tick {
+            index,
+            liquidity_net: self.liquidity_net,
+            liquidity_gross: self.liquidity_gross,
+            fee_growth_outside_x: self.fee_growth_outside_x,
+            fee_growth_outside_y: self.fee_growth_outside_y,
+        }
+    }
+}
+
+impl From<concentrated::Tick> for Tick {
+    fn from(tick: concentrated::Tick) -> Self {
+        Self {
+            liquidity_net: tick.liquidity_net,
+            liquidity_gross: tick.liquidity_gross,
+            fee_growth_outside_x: tick.fee_growth_outside_x,
+            fee_growth_outside_y: tick.fee_growth_outside_y,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions},
+    state::Mint as MintState,
+};
+use anchor_spl::token_interface::Mint;
+
+use crate::error::AmmError;
+
+// Token-2022 mints with the transfer fee extension withhold part of every transfer
+// in the destination account. The pool maths always run on what actually lands.
+
+fn transfer_fee_config(mint: &InterfaceAccount<Mint>) -> Result<Option<TransferFeeConfig>> {
+    let info = mint.to_account_info();
+    if *info.owner != anchor_spl::token_2022::ID {
+        return Ok(None);
+    }
+
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    Ok(state.get_extension::<TransferFeeConfig>().ok().copied())
+}
+
+// Fee withheld when `amount` is sent
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    match transfer_fee_config(mint)? {
+        Some(config) => config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(error!(AmmError::Overflow)),
+        None => Ok(0),
+    }
+}
+
+// Amount that arrives when `amount` is sent
+pub fn amount_after_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let fee = transfer_fee(mint, amount)?;
+    amount.checked_sub(fee).ok_or(error!(AmmError::Underflow))
+}
+
+// Amount to send so that `post_fee_amount` arrives
+pub fn amount_before_fee(mint: &InterfaceAccount<Mint>, post_fee_amount: u64) -> Result<u64> {
+    let fee = match transfer_fee_config(mint)? {
+        Some(config) => config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, post_fee_amount)
+            .ok_or(AmmError::Overflow)?,
+        None => 0,
+    };
+    post_fee_amount.checked_add(fee).ok_or(error!(AmmError::Overflow))
+}
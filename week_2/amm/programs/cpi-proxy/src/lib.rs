@@ -0,0 +1,47 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+};
+
+declare_id!("5bys3GvodPcZo4atgEmVPyWdrvxcyzFuVvoP3EcvhhPo");
+
+// Only deployed by the amm tests, to check what the amm refuses to do when it is
+// called from another program rather than from the transaction
+#[program]
+pub mod cpi_proxy {
+    use super::*;
+
+    // Calls `program` with `data` and the remaining accounts as given, signers
+    // included, so the callee sees the same instruction one level down
+    pub fn forward<'info>(ctx: Context<'_, '_, 'info, 'info, Forward<'info>>, data: Vec<u8>) -> Result<()> {
+        let accounts = ctx.remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect();
+
+        let instruction = Instruction {
+            program_id: ctx.accounts.program.key(),
+            accounts,
+            data,
+        };
+
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.program.to_account_info());
+        invoke(&instruction, &account_infos)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Forward<'info> {
+    /// CHECK: any program, it is only invoked
+    #[account(executable)]
+    pub program: UncheckedAccount<'info>,
+}
@@ -1,16 +1,1008 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
+import { Program, BN, web3 } from "@coral-xyz/anchor";
+import { assert } from "chai";
 import { Amm } from "../target/types/amm";
+import { CpiProxy } from "../target/types/cpi_proxy";
+
+const TOKEN_PROGRAM_ID = new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const TOKEN_2022_PROGRAM_ID = new web3.PublicKey("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+const ASSOCIATED_TOKEN_PROGRAM_ID = new web3.PublicKey("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+// cloned from mainnet by Anchor.toml
+const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+const MINT_SIZE = 82;
+// The base account length, its account type byte and the TransferFeeConfig entry
+const MINT_WITH_TRANSFER_FEE_SIZE = 165 + 1 + 4 + 108;
+
+// Sends tx and checks it fails with the program error `code`
+const expectError = async (tx: Promise<unknown>, code: string) => {
+  try {
+    await tx;
+  } catch (err) {
+    const logs: string[] = (err as { logs?: string[] }).logs ?? [];
+    assert.include(`${err}\n${logs.join("\n")}`, code);
+    return;
+  }
+  assert.fail(`expected ${code}`);
+};
 
 describe("amm", () => {
-  // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
 
   const program = anchor.workspace.amm as Program<Amm>;
+  // calls the amm through CPI, see programs/cpi-proxy
+  const proxy = anchor.workspace.cpiProxy as Program<CpiProxy>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const send = (instructions: web3.TransactionInstruction[], signers: web3.Keypair[]) =>
+    web3.sendAndConfirmTransaction(provider.connection, new web3.Transaction().add(...instructions), [payer, ...signers]);
+
+  // A bare mint under tokenProgram with payer as mint authority, or with
+  // transferFeeBps a Token-2022 mint that withholds that much of every transfer
+  const createMint = async (
+    tokenProgram: web3.PublicKey,
+    decimals: number,
+    transferFeeBps?: number
+  ): Promise<web3.PublicKey> => {
+    const mint = web3.Keypair.generate();
+    const space = transferFeeBps === undefined ? MINT_SIZE : MINT_WITH_TRANSFER_FEE_SIZE;
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(space);
+
+    const instructions = [
+      web3.SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint.publicKey,
+        space,
+        lamports,
+        programId: tokenProgram,
+      }),
+    ];
+
+    if (transferFeeBps !== undefined) {
+      // InitializeTransferFeeConfig: extension tag, instruction, no config or
+      // withdraw authority, basis points, maximum fee
+      const data = Buffer.alloc(14);
+      data.writeUInt8(26, 0);
+      data.writeUInt8(0, 1);
+      data.writeUInt16LE(transferFeeBps, 4);
+      new BN(1_000_000_000).toArrayLike(Buffer, "le", 8).copy(data, 6);
+
+      instructions.push(
+        new web3.TransactionInstruction({
+          programId: tokenProgram,
+          keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+          data,
+        })
+      );
+    }
+
+    // InitializeMint2: tag, decimals, mint authority, no freeze authority
+    const data = Buffer.alloc(35);
+    data.writeUInt8(20, 0);
+    data.writeUInt8(decimals, 1);
+    payer.publicKey.toBuffer().copy(data, 2);
+
+    instructions.push(
+      new web3.TransactionInstruction({
+        programId: tokenProgram,
+        keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+        data,
+      })
+    );
+
+    await send(instructions, [mint]);
+    return mint.publicKey;
+  };
+
+  const ata = (owner: web3.PublicKey, mint: web3.PublicKey, tokenProgram: web3.PublicKey): web3.PublicKey =>
+    web3.PublicKey.findProgramAddressSync(
+      [owner.toBuffer(), tokenProgram.toBuffer(), mint.toBuffer()],
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    )[0];
+
+  const metadataAddress = (mint: web3.PublicKey): web3.PublicKey =>
+    web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    )[0];
+
+  // Key, update authority, mint, then the name and symbol as borsh strings
+  // padded with zeros by token metadata
+  const readMetadata = (data: Buffer) => {
+    let offset = 1;
+    const readKey = () => {
+      const key = new web3.PublicKey(data.subarray(offset, offset + 32));
+      offset += 32;
+      return key;
+    };
+    const readString = () => {
+      const length = data.readUInt32LE(offset);
+      offset += 4;
+      const value = data.subarray(offset, offset + length).toString("utf8").replace(/\0/g, "");
+      offset += length;
+      return value;
+    };
+
+    const updateAuthority = readKey();
+    const mint = readKey();
+    const name = readString();
+    const symbol = readString();
+    return { updateAuthority, mint, name, symbol };
+  };
+
+  // Creates owner's associated account for mint and mints `amount` into it
+  const fund = async (
+    owner: web3.PublicKey,
+    mint: web3.PublicKey,
+    tokenProgram: web3.PublicKey,
+    amount: number
+  ): Promise<web3.PublicKey> => {
+    const account = ata(owner, mint, tokenProgram);
+
+    // MintTo: tag, amount
+    const data = Buffer.alloc(9);
+    data.writeUInt8(7, 0);
+    new BN(amount).toArrayLike(Buffer, "le", 8).copy(data, 1);
+
+    await send(
+      [
+        // CreateIdempotent
+        new web3.TransactionInstruction({
+          programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+          keys: [
+            { pubkey: payer.publicKey, isSigner: true, isWritable: true },
+            { pubkey: account, isSigner: false, isWritable: true },
+            { pubkey: owner, isSigner: false, isWritable: false },
+            { pubkey: mint, isSigner: false, isWritable: false },
+            { pubkey: web3.SystemProgram.programId, isSigner: false, isWritable: false },
+            { pubkey: tokenProgram, isSigner: false, isWritable: false },
+          ],
+          data: Buffer.from([1]),
+        }),
+        new web3.TransactionInstruction({
+          programId: tokenProgram,
+          keys: [
+            { pubkey: mint, isSigner: false, isWritable: true },
+            { pubkey: account, isSigner: false, isWritable: true },
+            { pubkey: payer.publicKey, isSigner: true, isWritable: false },
+          ],
+          data,
+        }),
+      ],
+      []
+    );
+    return account;
+  };
+
+  const balance = async (account: web3.PublicKey): Promise<number> => {
+    const info = await provider.connection.getAccountInfo(account);
+    if (info === null) {
+      return 0;
+    }
+    return Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
+  };
+
+  const newWallet = async (): Promise<web3.Keypair> => {
+    const wallet = web3.Keypair.generate();
+    await send(
+      [
+        web3.SystemProgram.transfer({
+          fromPubkey: payer.publicKey,
+          toPubkey: wallet.publicKey,
+          lamports: web3.LAMPORTS_PER_SOL,
+        }),
+      ],
+      []
+    );
+    return wallet;
+  };
+
+  type Pool = {
+    mintX: web3.PublicKey;
+    mintY: web3.PublicKey;
+    config: web3.PublicKey;
+    mintLp: web3.PublicKey;
+    vaultX: web3.PublicKey;
+    vaultY: web3.PublicKey;
+    tokenProgram: web3.PublicKey;
+    tokenProgramX: web3.PublicKey;
+    tokenProgramY: web3.PublicKey;
+  };
+
+  type Side = { mint: web3.PublicKey; tokenProgram: web3.PublicKey };
+
+  type PoolOptions = {
+    // the two mints in either order, two fresh classic mints when left out
+    sides?: [Side, Side];
+    feeTier?: number;
+    lpDecimals?: number;
+    lpTokenProgram?: web3.PublicKey;
+    authority?: web3.PublicKey | null;
+    // opens a StableSwap pool with this amplification instead of constant product
+    amp?: number;
+  };
+
+  // Addresses of the pool for x and y at feeTier, initialize expects x < y
+  const poolFor = (x: Side, y: Side, feeTier: number, lpTokenProgram: web3.PublicKey): Pool => {
+    const [config] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), x.mint.toBuffer(), y.mint.toBuffer(), new BN(feeTier).toArrayLike(Buffer, "le", 2)],
+      program.programId
+    );
+    const [mintLp] = web3.PublicKey.findProgramAddressSync([Buffer.from("lp"), config.toBuffer()], program.programId);
+
+    return {
+      mintX: x.mint,
+      mintY: y.mint,
+      config,
+      mintLp,
+      vaultX: ata(config, x.mint, x.tokenProgram),
+      vaultY: ata(config, y.mint, y.tokenProgram),
+      tokenProgram: lpTokenProgram,
+      tokenProgramX: x.tokenProgram,
+      tokenProgramY: y.tokenProgram,
+    };
+  };
+
+  const openPool = (
+    pool: Pool,
+    feeTier: number,
+    { lpDecimals = 6, authority = null, amp }: Pick<PoolOptions, "lpDecimals" | "authority" | "amp"> = {}
+  ) =>
+    program.methods
+      .initialize(
+        feeTier,
+        lpDecimals,
+        amp === undefined ? { constantProduct: {} } : { stableSwap: {} },
+        new BN(amp ?? 0),
+        authority
+      )
+      .accountsPartial({
+        initializer: payer.publicKey,
+        ...pool,
+        metadataLp: metadataAddress(pool.mintLp),
+        metadataX: metadataAddress(pool.mintX),
+        metadataY: metadataAddress(pool.mintY),
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        sysvarInstructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .rpc();
+
+  // Opens a pool with the sides sorted into x and y
+  const initializePool = async ({
+    sides,
+    feeTier = 30,
+    lpTokenProgram = TOKEN_PROGRAM_ID,
+    ...options
+  }: PoolOptions = {}): Promise<Pool> => {
+    const [first, second] = sides ?? [
+      { mint: await createMint(TOKEN_PROGRAM_ID, 6), tokenProgram: TOKEN_PROGRAM_ID },
+      { mint: await createMint(TOKEN_PROGRAM_ID, 6), tokenProgram: TOKEN_PROGRAM_ID },
+    ];
+    const [x, y] = Buffer.compare(first.mint.toBuffer(), second.mint.toBuffer()) < 0 ? [first, second] : [second, first];
+
+    const pool = poolFor(x, y, feeTier, lpTokenProgram);
+    await openPool(pool, feeTier, options);
+    return pool;
+  };
+
+  // Accounts every instruction on a pool names the same way, the system and
+  // associated token programs are filled in from the IDL
+  const poolAccounts = (pool: Pool) => ({
+    mintX: pool.mintX,
+    mintY: pool.mintY,
+    config: pool.config,
+    vaultX: pool.vaultX,
+    vaultY: pool.vaultY,
+    tokenProgramX: pool.tokenProgramX,
+    tokenProgramY: pool.tokenProgramY,
+  });
+
+  const deposit = (pool: Pool, user: web3.Keypair, amount: number, maxX: number, maxY: number) =>
+    program.methods
+      .deposit(new BN(amount), new BN(maxX), new BN(maxY))
+      .accountsPartial({
+        user: user.publicKey,
+        ...poolAccounts(pool),
+        mintLp: pool.mintLp,
+        userX: ata(user.publicKey, pool.mintX, pool.tokenProgramX),
+        userY: ata(user.publicKey, pool.mintY, pool.tokenProgramY),
+        userLp: ata(user.publicKey, pool.mintLp, pool.tokenProgram),
+        lockedLp: ata(pool.config, pool.mintLp, pool.tokenProgram),
+        tokenProgram: pool.tokenProgram,
+      })
+      .signers([user])
+      .rpc();
+
+  // A fresh wallet that makes the first deposit of x and y into the pool
+  const addLiquidity = async (pool: Pool, x: number, y: number): Promise<web3.Keypair> => {
+    const user = await newWallet();
+    await fund(user.publicKey, pool.mintX, pool.tokenProgramX, x);
+    await fund(user.publicKey, pool.mintY, pool.tokenProgramY, y);
+    await deposit(pool, user, 1, x, y);
+    return user;
+  };
+
+  const swap = (pool: Pool, user: web3.Keypair, isX: boolean, amountIn: number, minAmountOut: number) =>
+    program.methods
+      .swap(isX, new BN(amountIn), new BN(minAmountOut))
+      .accountsPartial({
+        user: user.publicKey,
+        ...poolAccounts(pool),
+        mintLp: pool.mintLp,
+        userX: ata(user.publicKey, pool.mintX, pool.tokenProgramX),
+        userY: ata(user.publicKey, pool.mintY, pool.tokenProgramY),
+      })
+      .signers([user])
+      .rpc();
+
+  // userIn defaults to the user's account for the side being deposited
+  const depositSingle = (
+    pool: Pool,
+    user: web3.Keypair,
+    isX: boolean,
+    amountIn: number,
+    minLpOut: number,
+    userIn = isX
+      ? ata(user.publicKey, pool.mintX, pool.tokenProgramX)
+      : ata(user.publicKey, pool.mintY, pool.tokenProgramY)
+  ) =>
+    program.methods
+      .depositSingle(isX, new BN(amountIn), new BN(minLpOut))
+      .accountsPartial({
+        user: user.publicKey,
+        ...poolAccounts(pool),
+        mintLp: pool.mintLp,
+        userIn,
+        userLp: ata(user.publicKey, pool.mintLp, pool.tokenProgram),
+        tokenProgram: pool.tokenProgram,
+      })
+      .signers([user])
+      .rpc();
+
+  const withdraw = (pool: Pool, user: web3.Keypair, lpAmount: number, minX: number, minY: number) =>
+    program.methods
+      .withdraw(new BN(lpAmount), new BN(minX), new BN(minY))
+      .accountsPartial({
+        user: user.publicKey,
+        ...poolAccounts(pool),
+        mintLp: pool.mintLp,
+        userX: ata(user.publicKey, pool.mintX, pool.tokenProgramX),
+        userY: ata(user.publicKey, pool.mintY, pool.tokenProgramY),
+        userLp: ata(user.publicKey, pool.mintLp, pool.tokenProgram),
+        tokenProgram: pool.tokenProgram,
+      })
+      .signers([user])
+      .rpc();
+
+  // A hop of route_swap's remaining accounts, trading mintIn for the pool's other side
+  const hop = (pool: Pool, user: web3.PublicKey, mintIn: web3.PublicKey): web3.AccountMeta[] => {
+    const isX = pool.mintX.equals(mintIn);
+    const [mintOut, vaultIn, vaultOut, programIn, programOut] = isX
+      ? [pool.mintY, pool.vaultX, pool.vaultY, pool.tokenProgramX, pool.tokenProgramY]
+      : [pool.mintX, pool.vaultY, pool.vaultX, pool.tokenProgramY, pool.tokenProgramX];
+
+    return [
+      { pubkey: pool.config, isSigner: false, isWritable: true },
+      { pubkey: mintIn, isSigner: false, isWritable: false },
+      { pubkey: mintOut, isSigner: false, isWritable: false },
+      { pubkey: vaultIn, isSigner: false, isWritable: true },
+      { pubkey: vaultOut, isSigner: false, isWritable: true },
+      { pubkey: ata(user, mintIn, programIn), isSigner: false, isWritable: true },
+      { pubkey: ata(user, mintOut, programOut), isSigner: false, isWritable: true },
+      { pubkey: programIn, isSigner: false, isWritable: false },
+      { pubkey: programOut, isSigner: false, isWritable: false },
+    ];
+  };
+
+  const routeSwap = (user: web3.Keypair, hops: web3.AccountMeta[], amountIn: number, minAmountOut: number) =>
+    program.methods
+      .routeSwap(new BN(amountIn), new BN(minAmountOut))
+      .accountsPartial({ user: user.publicKey })
+      .remainingAccounts(hops)
+      .signers([user])
+      .rpc();
+
+  const flashLoan = (pool: Pool, borrower: web3.Keypair, isX: boolean, amount: number) =>
+    program.methods
+      .flashLoan(isX, new BN(amount))
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        ...poolAccounts(pool),
+        borrowerX: ata(borrower.publicKey, pool.mintX, pool.tokenProgramX),
+        borrowerY: ata(borrower.publicKey, pool.mintY, pool.tokenProgramY),
+        instructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .instruction();
+
+  const flashRepay = (pool: Pool, borrower: web3.Keypair) =>
+    program.methods
+      .flashRepay()
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        ...poolAccounts(pool),
+        borrowerX: ata(borrower.publicKey, pool.mintX, pool.tokenProgramX),
+        borrowerY: ata(borrower.publicKey, pool.mintY, pool.tokenProgramY),
+      })
+      .instruction();
+
+  // Anyone can crank the collection, the fees only ever go to feeRecipient's accounts
+  const collectProtocolFees = (pool: Pool, feeRecipient: web3.PublicKey) =>
+    program.methods
+      .collectProtocolFees()
+      .accountsPartial({
+        payer: payer.publicKey,
+        ...poolAccounts(pool),
+        feeRecipient,
+        feeRecipientX: ata(feeRecipient, pool.mintX, pool.tokenProgramX),
+        feeRecipientY: ata(feeRecipient, pool.mintY, pool.tokenProgramY),
+      })
+      .rpc();
+
+  // Accounts for the admin instructions, signed by `authority`
+  const update = (pool: Pool, authority: web3.Keypair) => ({ authority: authority.publicKey, config: pool.config });
+
+  // The validator's clock, which is what ramps and the oracle run on
+  const chainTime = async (): Promise<number> =>
+    provider.connection.getBlockTime(await provider.connection.getSlot());
+
+  const waitUntil = async (timestamp: number) => {
+    while ((await chainTime()) < timestamp) {
+      await new Promise((resolve) => setTimeout(resolve, 500));
+    }
+  };
+
+  for (const [label, lpTokenProgram] of [
+    ["the token program", TOKEN_PROGRAM_ID],
+    ["Token-2022", TOKEN_2022_PROGRAM_ID],
+  ] as const) {
+    it(`names the LP token after the pair with its mint under ${label}`, async () => {
+      const { mintX, mintY, config, mintLp } = await initializePool({ lpTokenProgram });
+
+      const lpMint = await provider.connection.getAccountInfo(mintLp);
+      assert.isTrue(lpMint.owner.equals(lpTokenProgram));
+
+      const metadata = await provider.connection.getAccountInfo(metadataAddress(mintLp));
+      assert.isNotNull(metadata, "LP metadata was not created");
+      assert.isTrue(metadata.owner.equals(TOKEN_METADATA_PROGRAM_ID));
+
+      // neither mint has metadata, so each side goes by the start of its address
+      const pair = `${mintX.toBase58().slice(0, 4)}-${mintY.toBase58().slice(0, 4)}`;
+      const { updateAuthority, mint, name, symbol } = readMetadata(metadata.data);
+      assert.isTrue(mint.equals(mintLp));
+      assert.isTrue(updateAuthority.equals(config));
+      assert.equal(name, `${pair} LP`);
+      assert.equal(symbol, pair);
+    });
+  }
+
+  it("swaps net of the pool fee and rejects an output short of the minimum", async () => {
+    const pool = await initializePool();
+    await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    const trader = await newWallet();
+    await fund(trader.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+
+    // 30 of the 10_000 is the fee, the other 9_970 buy 1_000_000 × 9_970 / 1_009_970 of y
+    await expectError(swap(pool, trader, true, 10_000, 9_872), "SlippageExceeded");
+    await swap(pool, trader, true, 10_000, 9_871);
+
+    assert.equal(await balance(ata(trader.publicKey, pool.mintX, pool.tokenProgramX)), 0);
+    assert.equal(await balance(ata(trader.publicKey, pool.mintY, pool.tokenProgramY)), 9_871);
+    assert.equal(await balance(pool.vaultX), 1_010_000);
+    assert.equal(await balance(pool.vaultY), 990_129);
+  });
+
+  it("rejects a swap of nothing", async () => {
+    const pool = await initializePool();
+    await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    const trader = await newWallet();
+    await fund(trader.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+
+    await expectError(swap(pool, trader, true, 0, 0), "InvalidAmount");
+  });
+
+  it("burns LP for its share of both reserves and rejects a short withdrawal", async () => {
+    const pool = await initializePool();
+    const user = await addLiquidity(pool, 1_000_000, 1_000_000);
+    const userLp = ata(user.publicKey, pool.mintLp, pool.tokenProgram);
+
+    // 999_000 LP went to the user and 1_000 were locked, so half of the user's is 499_500 of 1_000_000
+    await expectError(withdraw(pool, user, 499_500, 499_501, 0), "SlippageExceeded");
+    await expectError(withdraw(pool, user, 499_500, 0, 499_501), "SlippageExceeded");
+    await withdraw(pool, user, 499_500, 499_500, 499_500);
+
+    assert.equal(await balance(userLp), 499_500);
+    assert.equal(await balance(ata(user.publicKey, pool.mintX, pool.tokenProgramX)), 499_500);
+    assert.equal(await balance(ata(user.publicKey, pool.mintY, pool.tokenProgramY)), 499_500);
+    assert.equal(await balance(pool.vaultX), 500_500);
+    assert.equal(await balance(pool.vaultY), 500_500);
+  });
+
+  it("rejects withdrawing more LP than the user holds", async () => {
+    const pool = await initializePool();
+    const user = await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    await expectError(withdraw(pool, user, 999_001, 0, 0), "InsufficientBalance");
+    await expectError(withdraw(pool, user, 0, 0, 0), "InvalidAmount");
+  });
+
+  it("mints sqrt(xy) LP on the first deposit with the minimum liquidity locked", async () => {
+    const pool = await initializePool();
+    const user = await newWallet();
+    await fund(user.publicKey, pool.mintX, pool.tokenProgramX, 4_000_000);
+    await fund(user.publicKey, pool.mintY, pool.tokenProgramY, 1_000_000);
+
+    // sqrt(4_000_000 × 1_000_000) = 2_000_000, less the 1_000 locked
+    await expectError(deposit(pool, user, 1_999_001, 4_000_000, 1_000_000), "SlippageExceeded");
+    await deposit(pool, user, 1_999_000, 4_000_000, 1_000_000);
+
+    assert.equal(await balance(ata(user.publicKey, pool.mintLp, pool.tokenProgram)), 1_999_000);
+    assert.equal(await balance(ata(pool.config, pool.mintLp, pool.tokenProgram)), 1_000);
+    assert.equal((await provider.connection.getTokenSupply(pool.mintLp)).value.amount, "2000000");
+  });
+
+  it("rejects a first deposit that would not cover the minimum liquidity", async () => {
+    const pool = await initializePool();
+    const user = await newWallet();
+    await fund(user.publicKey, pool.mintX, pool.tokenProgramX, 1_000);
+    await fund(user.publicKey, pool.mintY, pool.tokenProgramY, 1_000);
+
+    await expectError(deposit(pool, user, 1, 1_000, 1_000), "LiquidityLessThanMinimum");
+  });
+
+  it("charges later deposits the pool ratio for the LP asked for, rounded up", async () => {
+    const pool = await initializePool();
+    await addLiquidity(pool, 4_000_000, 1_000_000);
+
+    const user = await newWallet();
+    const userX = await fund(user.publicKey, pool.mintX, pool.tokenProgramX, 1_000_000);
+    const userY = await fund(user.publicKey, pool.mintY, pool.tokenProgramY, 1_000_000);
+
+    // a tenth of the 2_000_000 supply costs a tenth of each reserve
+    await expectError(deposit(pool, user, 200_000, 399_999, 100_000), "SlippageExceeded");
+    await expectError(deposit(pool, user, 200_000, 400_000, 99_999), "SlippageExceeded");
+    await deposit(pool, user, 200_000, 400_000, 100_000);
+
+    assert.equal(await balance(ata(user.publicKey, pool.mintLp, pool.tokenProgram)), 200_000);
+    assert.equal(await balance(userX), 600_000);
+    assert.equal(await balance(userY), 900_000);
+
+    // 1 LP of 2_200_000 is worth less than one unit of y, and still costs one
+    await deposit(pool, user, 1, 2, 1);
+    assert.equal(await balance(userX), 599_998);
+    assert.equal(await balance(userY), 899_999);
+  });
+
+  it("lets only the authority lock the pool, and a locked pool refuses trades and withdrawals", async () => {
+    const admin = await newWallet();
+    const outsider = await newWallet();
+    const pool = await initializePool({ authority: admin.publicKey });
+    const user = await addLiquidity(pool, 1_000_000, 1_000_000);
+    await fund(user.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+
+    await expectError(
+      program.methods.lock().accountsPartial(update(pool, outsider)).signers([outsider]).rpc(),
+      "InvalidAuthority"
+    );
+    await program.methods.lock().accountsPartial(update(pool, admin)).signers([admin]).rpc();
+    assert.isTrue((await program.account.config.fetch(pool.config)).locked);
+
+    await expectError(swap(pool, user, true, 10_000, 0), "PoolLocked");
+    await expectError(withdraw(pool, user, 1_000, 0, 0), "PoolLocked");
+
+    await program.methods.unlock().accountsPartial(update(pool, admin)).signers([admin]).rpc();
+    await swap(pool, user, true, 10_000, 0);
+    await withdraw(pool, user, 1_000, 0, 0);
+  });
+
+  it("lets the authority change the fee up to the maximum", async () => {
+    const admin = await newWallet();
+    const pool = await initializePool({ authority: admin.publicKey });
+    await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    await expectError(
+      program.methods.updateFee(1_001).accountsPartial(update(pool, admin)).signers([admin]).rpc(),
+      "InvalidFee"
+    );
+    await program.methods.updateFee(100).accountsPartial(update(pool, admin)).signers([admin]).rpc();
+
+    const config = await program.account.config.fetch(pool.config);
+    assert.equal(config.fee, 100);
+    // the tier the pool is keyed by stays as it was opened
+    assert.equal(config.feeTier, 30);
+
+    // 1% of the 10_000 in is now kept, 1_000_000 × 9_900 / 1_009_900 of y comes out
+    const trader = await newWallet();
+    await fund(trader.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+    await swap(pool, trader, true, 10_000, 9_802);
+    assert.equal(await balance(ata(trader.publicKey, pool.mintY, pool.tokenProgramY)), 9_802);
+  });
+
+  it("hands the authority over and leaves a renounced pool without admin controls", async () => {
+    const admin = await newWallet();
+    const successor = await newWallet();
+    const pool = await initializePool({ authority: admin.publicKey });
+
+    await program.methods.setAuthority(successor.publicKey).accountsPartial(update(pool, admin)).signers([admin]).rpc();
+    assert.isTrue((await program.account.config.fetch(pool.config)).authority.equals(successor.publicKey));
+    await expectError(
+      program.methods.lock().accountsPartial(update(pool, admin)).signers([admin]).rpc(),
+      "InvalidAuthority"
+    );
+
+    await program.methods.renounceAuthority().accountsPartial(update(pool, successor)).signers([successor]).rpc();
+    assert.isNull((await program.account.config.fetch(pool.config)).authority);
+    await expectError(
+      program.methods.lock().accountsPartial(update(pool, successor)).signers([successor]).rpc(),
+      "NoAuthoritySet"
+    );
+  });
+
+  it("accrues the protocol share of swap fees outside the reserves and pays it to the recipient", async () => {
+    const admin = await newWallet();
+    const pool = await initializePool({ authority: admin.publicKey });
+    await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    // a PDA can receive the fees as well as a wallet
+    const [feeRecipient] = web3.PublicKey.findProgramAddressSync([Buffer.from("fees")], web3.Keypair.generate().publicKey);
+
+    await expectError(
+      program.methods.setProtocolFee(5_001, feeRecipient).accountsPartial(update(pool, admin)).signers([admin]).rpc(),
+      "InvalidFee"
+    );
+    await program.methods.setProtocolFee(5_000, feeRecipient).accountsPartial(update(pool, admin)).signers([admin]).rpc();
+
+    // half of the 30 fee on 10_000 in
+    const trader = await newWallet();
+    await fund(trader.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+    await swap(pool, trader, true, 10_000, 0);
+
+    let config = await program.account.config.fetch(pool.config);
+    assert.equal(config.protocolFeesX.toNumber(), 15);
+    assert.equal(config.protocolFeesY.toNumber(), 0);
+
+    await expectError(collectProtocolFees(pool, admin.publicKey), "InvalidFeeRecipient");
+    await collectProtocolFees(pool, feeRecipient);
+
+    config = await program.account.config.fetch(pool.config);
+    assert.equal(config.protocolFeesX.toNumber(), 0);
+    assert.equal(await balance(ata(feeRecipient, pool.mintX, pool.tokenProgramX)), 15);
+    assert.equal(await balance(pool.vaultX), 1_010_000 - 15);
+
+    // nothing more is owed until the next swap
+    await collectProtocolFees(pool, feeRecipient);
+    assert.equal(await balance(ata(feeRecipient, pool.mintX, pool.tokenProgramX)), 15);
+  });
+
+  it("prices a Token-2022 transfer-fee mint on what reaches the vault and what the user keeps", async () => {
+    const feeMint = await createMint(TOKEN_2022_PROGRAM_ID, 6, 100);
+    const plainMint = await createMint(TOKEN_PROGRAM_ID, 6);
+    const pool = await initializePool({
+      sides: [
+        { mint: feeMint, tokenProgram: TOKEN_2022_PROGRAM_ID },
+        { mint: plainMint, tokenProgram: TOKEN_PROGRAM_ID },
+      ],
+    });
+    const feeIsX = pool.mintX.equals(feeMint);
+    const feeVault = feeIsX ? pool.vaultX : pool.vaultY;
+
+    // 1% of the 1_000_000 sent is withheld, so the LP is sqrt(990_000 × 1_000_000) less the 1_000 locked
+    const user = await newWallet();
+    await fund(user.publicKey, feeMint, TOKEN_2022_PROGRAM_ID, 1_000_000);
+    await fund(user.publicKey, plainMint, TOKEN_PROGRAM_ID, 1_000_000);
+    await deposit(pool, user, 993_987, 1_000_000, 1_000_000);
+
+    assert.equal(await balance(feeVault), 990_000);
+    assert.equal(await balance(ata(user.publicKey, pool.mintLp, pool.tokenProgram)), 993_987);
+
+    const trader = await newWallet();
+    const traderFee = await fund(trader.publicKey, feeMint, TOKEN_2022_PROGRAM_ID, 10_000);
+    const traderPlain = await fund(trader.publicKey, plainMint, TOKEN_PROGRAM_ID, 10_000);
+
+    // only the 9_900 that arrives is priced
+    await swap(pool, trader, feeIsX, 10_000, 9_871);
+    assert.equal(await balance(feeVault), 999_900);
+    assert.equal(await balance(traderFee), 0);
+    assert.equal(await balance(traderPlain), 19_871);
+
+    // the vault sends 9_968 and 100 of it is withheld on the way, the minimum is checked against the rest
+    await expectError(swap(pool, trader, !feeIsX, 10_000, 9_869), "SlippageExceeded");
+    await swap(pool, trader, !feeIsX, 10_000, 9_868);
+    assert.equal(await balance(feeVault), 999_900 - 9_968);
+    assert.equal(await balance(traderFee), 9_868);
+  });
+
+  it("opens one pool per sorted mint pair and fee tier", async () => {
+    const pool = await initializePool();
+    const x = { mint: pool.mintX, tokenProgram: pool.tokenProgramX };
+    const y = { mint: pool.mintY, tokenProgram: pool.tokenProgramY };
+
+    // the same pair and tier again, and the pair the other way round
+    await expectError(openPool(poolFor(x, y, 30, TOKEN_PROGRAM_ID), 30), "already in use");
+    await expectError(openPool(poolFor(y, x, 30, TOKEN_PROGRAM_ID), 30), "UnsortedMints");
+
+    const other = await initializePool({ sides: [y, x], feeTier: 5 });
+    assert.isFalse(other.config.equals(pool.config));
+    assert.isTrue(other.mintX.equals(pool.mintX));
+
+    const config = await program.account.config.fetch(other.config);
+    assert.equal(config.feeTier, 5);
+    assert.equal(config.fee, 5);
+  });
+
+  it("gives the LP mint the decimals asked for, up to nine", async () => {
+    const pool = await initializePool({ lpDecimals: 9 });
+    assert.equal((await provider.connection.getTokenSupply(pool.mintLp)).value.decimals, 9);
+
+    await expectError(initializePool({ lpDecimals: 10 }), "InvalidPrecision");
+  });
+
+  it("rejects a stable pool opened with an amplification out of range", async () => {
+    await expectError(initializePool({ amp: 0 }), "InvalidAmp");
+    await expectError(initializePool({ amp: 1_000_001 }), "InvalidAmp");
+  });
+
+  it("prices a balanced stable pool closer to one to one than constant product", async () => {
+    const pool = await initializePool({ amp: 100 });
+    const user = await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    // a balanced pool's D is the sum of its reserves
+    assert.equal(await balance(ata(user.publicKey, pool.mintLp, pool.tokenProgram)), 1_999_000);
+
+    // constant product would pay 9_871 for the same 10_000
+    const trader = await newWallet();
+    await fund(trader.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+    await swap(pool, trader, true, 10_000, 9_969);
+    assert.equal(await balance(ata(trader.publicKey, pool.mintY, pool.tokenProgramY)), 9_969);
+  });
+
+  it("ramps the amplification by at most tenfold over at least a day, and stops it where it is", async () => {
+    const admin = await newWallet();
+    const pool = await initializePool({ amp: 100, authority: admin.publicKey });
+    const rampAmp = (targetAmp: number, rampStopTs: number) =>
+      program.methods
+        .rampAmp(new BN(targetAmp), new BN(rampStopTs))
+        .accountsPartial(update(pool, admin))
+        .signers([admin])
+        .rpc();
+
+    const stop = (await chainTime()) + 2 * 86_400;
+    await expectError(rampAmp(200, (await chainTime()) + 3_600), "InvalidRamp");
+    await expectError(rampAmp(1_001, stop), "InvalidRamp");
+    await expectError(rampAmp(9, stop), "InvalidRamp");
+    await expectError(rampAmp(0, stop), "InvalidAmp");
+
+    await rampAmp(1_000, stop);
+    let config = await program.account.config.fetch(pool.config);
+    assert.equal(config.initialAmp.toNumber(), 100);
+    assert.equal(config.targetAmp.toNumber(), 1_000);
+    assert.equal(config.rampStopTs.toNumber(), stop);
+
+    // seconds into a two day ramp the amplification has not moved off 100
+    await program.methods.stopRampAmp().accountsPartial(update(pool, admin)).signers([admin]).rpc();
+    config = await program.account.config.fetch(pool.config);
+    assert.equal(config.initialAmp.toNumber(), 100);
+    assert.equal(config.targetAmp.toNumber(), 100);
+    assert.equal(config.rampStopTs.toNumber(), config.rampStartTs.toNumber());
+  });
+
+  it("only ramps stable pools", async () => {
+    const admin = await newWallet();
+    const pool = await initializePool({ authority: admin.publicKey });
+    const stop = (await chainTime()) + 2 * 86_400;
+
+    await expectError(
+      program.methods
+        .rampAmp(new BN(200), new BN(stop))
+        .accountsPartial(update(pool, admin))
+        .signers([admin])
+        .rpc(),
+      "NotStableSwap"
+    );
+  });
+
+  it("accumulates each price for as long as it stood", async () => {
+    const pool = await initializePool();
+    await addLiquidity(pool, 1_000_000, 4_000_000);
+
+    // the first deposit had no reserves to price, it only starts the clock
+    const before = await program.account.config.fetch(pool.config);
+    assert.isTrue(before.priceXCumulative.isZero());
+    await waitUntil(before.lastUpdateTs.toNumber() + 2);
+
+    const trader = await newWallet();
+    await fund(trader.publicKey, pool.mintX, pool.tokenProgramX, 1_000);
+    await swap(pool, trader, true, 1_000, 0);
+
+    // the swap prices the reserves it found, x at 4 y and y at a quarter x, in Q64.64
+    const after = await program.account.config.fetch(pool.config);
+    const elapsed = after.lastUpdateTs.sub(before.lastUpdateTs);
+    assert.isAtLeast(elapsed.toNumber(), 2);
+    assert.equal(after.priceXCumulative.toString(), new BN(1).shln(66).mul(elapsed).toString());
+    assert.equal(after.priceYCumulative.toString(), new BN(1).shln(62).mul(elapsed).toString());
+  });
+
+  it("still withdraws from a stable pool swapped down to its last few units", async () => {
+    const admin = await newWallet();
+    const pool = await initializePool({ feeTier: 227, amp: 4_520, authority: admin.publicKey });
+    await program.methods.setProtocolFee(381, admin.publicKey).accountsPartial(update(pool, admin)).signers([admin]).rpc();
+    const user = await addLiquidity(pool, 1_809_748, 1_559_368);
+
+    const trader = await newWallet();
+    await fund(trader.publicKey, pool.mintX, pool.tokenProgramX, 12_449_126);
+    await swap(pool, trader, true, 12_449_126, 0);
+    assert.equal(await balance(ata(trader.publicKey, pool.mintY, pool.tokenProgramY)), 1_559_364);
+
+    // the reserves are now 14_248_108 x, net of the protocol fee, against 4 y, and
+    // the withdraw has to bring the oracle up to date across them first
+    const swapped = await program.account.config.fetch(pool.config);
+    await waitUntil(swapped.lastUpdateTs.toNumber() + 2);
+
+    await withdraw(pool, user, 3_368_114, 0, 0);
+
+    assert.equal(await balance(ata(user.publicKey, pool.mintLp, pool.tokenProgram)), 0);
+    assert.equal(await balance(ata(user.publicKey, pool.mintX, pool.tokenProgramX)), 14_243_878);
+    assert.equal(await balance(ata(user.publicKey, pool.mintY, pool.tokenProgramY)), 3);
+    assert.isAbove(
+      (await program.account.config.fetch(pool.config)).lastUpdateTs.toNumber(),
+      swapped.lastUpdateTs.toNumber()
+    );
+  });
+
+  // Pools for mints a-b and b-c, each seeded one to one, and a trader holding 10_000 of a
+  const route = async () => {
+    const [a, b, c] = [
+      { mint: await createMint(TOKEN_PROGRAM_ID, 6), tokenProgram: TOKEN_PROGRAM_ID },
+      { mint: await createMint(TOKEN_PROGRAM_ID, 6), tokenProgram: TOKEN_PROGRAM_ID },
+      { mint: await createMint(TOKEN_PROGRAM_ID, 6), tokenProgram: TOKEN_PROGRAM_ID },
+    ];
+    const first = await initializePool({ sides: [a, b] });
+    const second = await initializePool({ sides: [b, c] });
+    await addLiquidity(first, 1_000_000, 1_000_000);
+    await addLiquidity(second, 1_000_000, 1_000_000);
+
+    const trader = await newWallet();
+    const traderA = await fund(trader.publicKey, a.mint, TOKEN_PROGRAM_ID, 10_000);
+    const traderB = await fund(trader.publicKey, b.mint, TOKEN_PROGRAM_ID, 0);
+    const traderC = await fund(trader.publicKey, c.mint, TOKEN_PROGRAM_ID, 0);
+
+    return { a: a.mint, b: b.mint, c: c.mint, first, second, trader, traderA, traderB, traderC };
+  };
+
+  it("routes a swap through two pools and checks the minimum on the last output", async () => {
+    const { a, b, first, second, trader, traderA, traderB, traderC } = await route();
+    const hops = [...hop(first, trader.publicKey, a), ...hop(second, trader.publicKey, b)];
+
+    // 10_000 of a buys 9_871 of b, which buys 9_745 of c
+    await expectError(routeSwap(trader, hops, 10_000, 9_746), "SlippageExceeded");
+    await routeSwap(trader, hops, 10_000, 9_745);
+
+    assert.equal(await balance(traderA), 0);
+    assert.equal(await balance(traderB), 0);
+    assert.equal(await balance(traderC), 9_745);
+  });
+
+  it("rejects routes whose hops do not line up", async () => {
+    const { a, b, c, first, second, trader } = await route();
+    const firstHop = hop(first, trader.publicKey, a);
+
+    await expectError(routeSwap(trader, [], 10_000, 0), "InvalidRoute");
+    await expectError(routeSwap(trader, firstHop.slice(0, 8), 10_000, 0), "InvalidRoute");
+
+    // the second hop has to spend what the first one paid out
+    await expectError(
+      routeSwap(trader, [...firstHop, ...hop(second, trader.publicKey, c)], 10_000, 0),
+      "InvalidRoute"
+    );
+
+    // a vault from another pool
+    const wrongVault = [...firstHop];
+    wrongVault[4] = { ...wrongVault[4], pubkey: second.mintX.equals(b) ? second.vaultX : second.vaultY };
+    await expectError(routeSwap(trader, wrongVault, 10_000, 0), "InvalidRoute");
+
+    // more than four hops, there and back
+    const back = hop(first, trader.publicKey, b);
+    await expectError(
+      routeSwap(trader, [...firstHop, ...back, ...firstHop, ...back, ...firstHop], 10_000, 0),
+      "InvalidRoute"
+    );
+  });
+
+  // A pool seeded one to one and a borrower holding `x` of its x side and none of y
+  const flashPool = async (x: number) => {
+    const pool = await initializePool();
+    await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    const borrower = await newWallet();
+    const borrowerX = await fund(borrower.publicKey, pool.mintX, pool.tokenProgramX, x);
+    await fund(borrower.publicKey, pool.mintY, pool.tokenProgramY, 0);
+
+    return { pool, borrower, borrowerX };
+  };
+
+  it("lends out of a vault when the loan and its fee are repaid later in the transaction", async () => {
+    const { pool, borrower, borrowerX } = await flashPool(300);
+
+    // the fee is the pool's 0.3% of the 100_000 lent, and stays with the LPs
+    await send([await flashLoan(pool, borrower, true, 100_000), await flashRepay(pool, borrower)], [borrower]);
+
+    assert.equal(await balance(borrowerX), 0);
+    assert.equal(await balance(pool.vaultX), 1_000_300);
+    assert.isTrue((await program.account.config.fetch(pool.config)).flashLoanDue.isZero());
+  });
+
+  it("rejects a flash loan with no repayment of the same pool after it", async () => {
+    const { pool, borrower } = await flashPool(300);
+    const other = (await flashPool(300)).pool;
+
+    await expectError(send([await flashLoan(pool, borrower, true, 100_000)], [borrower]), "MissingFlashRepay");
+    await expectError(
+      send([await flashLoan(pool, borrower, true, 100_000), await flashRepay(other, borrower)], [borrower]),
+      "MissingFlashRepay"
+    );
+    await expectError(
+      send([await flashRepay(pool, borrower), await flashLoan(pool, borrower, true, 100_000)], [borrower]),
+      "NoFlashLoan"
+    );
+  });
+
+  it("fails the whole loan when the borrower cannot pay the fee on top", async () => {
+    // the borrower only has the 100_000 lent to repay the 100_300 due with
+    const { pool, borrower, borrowerX } = await flashPool(0);
+
+    await expectError(
+      send([await flashLoan(pool, borrower, true, 100_000), await flashRepay(pool, borrower)], [borrower]),
+      "insufficient funds"
+    );
+
+    assert.equal(await balance(borrowerX), 0);
+    assert.equal(await balance(pool.vaultX), 1_000_000);
+    assert.isTrue((await program.account.config.fetch(pool.config)).flashLoanDue.isZero());
+  });
+
+  it("refuses a flash loan taken through another program", async () => {
+    const { pool, borrower } = await flashPool(300);
+    const loan = await flashLoan(pool, borrower, true, 100_000);
+    const nested = await proxy.methods
+      .forward(loan.data)
+      .accountsPartial({ program: program.programId })
+      .remainingAccounts(loan.keys)
+      .instruction();
+
+    await expectError(send([nested, await flashRepay(pool, borrower)], [borrower]), "FlashLoanCpi");
+  });
+
+  it("mints LP for a single-sided deposit without paying anything out", async () => {
+    const pool = await initializePool();
+    await addLiquidity(pool, 1_000_000, 1_000_000);
+
+    const user = await newWallet();
+    const userX = await fund(user.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+
+    // 4_995 of the 10_000 is priced as a swap for y, and the whole balanced deposit is worth 4_979 LP
+    await expectError(depositSingle(pool, user, true, 10_000, 4_980), "SlippageExceeded");
+    await depositSingle(pool, user, true, 10_000, 4_979);
+
+    assert.equal(await balance(ata(user.publicKey, pool.mintLp, pool.tokenProgram)), 4_979);
+    assert.equal(await balance(userX), 0);
+    assert.equal(await balance(pool.vaultX), 1_010_000);
+    assert.equal(await balance(pool.vaultY), 1_000_000);
+  });
+
+  it("rejects a single-sided deposit into an empty pool or from the other side's account", async () => {
+    const pool = await initializePool();
+    const user = await newWallet();
+    await fund(user.publicKey, pool.mintX, pool.tokenProgramX, 10_000);
+    const userY = await fund(user.publicKey, pool.mintY, pool.tokenProgramY, 10_000);
+
+    // with no LP supply there is no ratio to deposit into
+    await expectError(depositSingle(pool, user, true, 10_000, 0), "ZeroBalance");
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+    await addLiquidity(pool, 1_000_000, 1_000_000);
+    await expectError(depositSingle(pool, user, true, 10_000, 0, userY), "InvalidMint");
   });
 });
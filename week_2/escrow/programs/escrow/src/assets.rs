@@ -0,0 +1,395 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+
+use anchor_spl::{
+    associated_token::{create_idempotent, get_associated_token_address_with_program_id, AssociatedToken, Create},
+    metadata::{
+        mpl_token_metadata::{self, instructions::TransferV1CpiBuilder},
+        Metadata,
+    },
+    token::Token,
+    token_interface::{close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::error::EscrowError;
+use crate::state::{Asset, AssetKind};
+
+// Basket instructions take each asset's accounts from remaining_accounts, asset
+// by asset in the order the basket lists them:
+//
+//   Token            mint, from, to, token_program
+//   ProgrammableNft  mint, from, to, metadata, edition, from_token_record,
+//                    to_token_record, authorization_rules
+//
+// `to` is the receiver's associated token account and is created when missing.
+// A pNFT without a rule set passes the token metadata program as
+// authorization_rules.
+const TOKEN_ACCOUNTS: usize = 4;
+const PROGRAMMABLE_NFT_ACCOUNTS: usize = 8;
+
+pub const AUTHORIZATION_RULES_PROGRAM_ID: Pubkey = pubkey!("auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg");
+
+// Sanity checks for one side of a basket at make
+pub fn check_assets(assets: &[Asset]) -> Result<()> {
+    require!(
+        (1..=crate::state::MAX_BASKET_ASSETS).contains(&assets.len()),
+        EscrowError::InvalidBasket
+    );
+
+    for (i, asset) in assets.iter().enumerate() {
+        require!(asset.amount != 0, EscrowError::ZeroAmount);
+        if asset.kind == AssetKind::ProgrammableNft {
+            require!(asset.amount == 1, EscrowError::InvalidBasket);
+        }
+        // one vault per mint
+        require!(
+            assets[..i].iter().all(|other| other.mint != asset.mint),
+            EscrowError::InvalidBasket
+        );
+    }
+
+    Ok(())
+}
+
+// Programs the basket instructions move assets through, the same on each of them.
+// Structs embedding it glob-import this module for the derive's generated code.
+#[derive(Accounts)]
+pub struct AssetPrograms<'info> {
+    /// CHECK: the instructions sysvar, pinned by address
+    #[account(address = sysvar_instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    /// CHECK: the token auth rules program, pinned by address
+    #[account(address = AUTHORIZATION_RULES_PROGRAM_ID)]
+    pub authorization_rules_program: UncheckedAccount<'info>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Moves a list of assets from one owner to another. Programs are shared by every
+// asset, per-asset accounts come from remaining_accounts.
+pub struct AssetTransfer<'a, 'info> {
+    // signs for `from`, with signer_seeds when it is the basket
+    from_owner: &'a AccountInfo<'info>,
+    to_owner: &'a AccountInfo<'info>,
+    payer: &'a AccountInfo<'info>,
+    signer_seeds: &'a [&'a [&'a [u8]]],
+    // Empties and closes the vaults to this account once they are paid out
+    close_to: Option<&'a AccountInfo<'info>>,
+
+    programs: &'a AssetPrograms<'info>,
+    system_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> AssetTransfer<'a, 'info> {
+    pub fn new(
+        from_owner: &'a AccountInfo<'info>,
+        to_owner: &'a AccountInfo<'info>,
+        payer: &'a AccountInfo<'info>,
+        signer_seeds: &'a [&'a [&'a [u8]]],
+        close_to: Option<&'a AccountInfo<'info>>,
+        programs: &'a AssetPrograms<'info>,
+        system_program: &'a AccountInfo<'info>,
+    ) -> Self {
+        Self { from_owner, to_owner, payer, signer_seeds, close_to, programs, system_program }
+    }
+
+    // Transfers every asset and returns the accounts it did not use
+    pub fn transfer_all(
+        &self,
+        assets: &[Asset],
+        accounts: &'a [AccountInfo<'info>],
+    ) -> Result<&'a [AccountInfo<'info>]> {
+        let (asset_accounts, unused) = split_accounts(assets, accounts)?;
+
+        for (asset, accounts) in assets.iter().zip(asset_accounts) {
+            match asset.kind {
+                AssetKind::Token => self.transfer_token(asset, accounts)?,
+                AssetKind::ProgrammableNft => self.transfer_programmable_nft(accounts)?,
+            }
+        }
+
+        Ok(unused)
+    }
+
+    fn transfer_token(&self, asset: &Asset, accounts: &'a [AccountInfo<'info>]) -> Result<()> {
+        let [mint, from, to, token_program] = accounts else {
+            return err!(EscrowError::InvalidRemainingAccounts);
+        };
+
+        // the mint's owner is its token program, and it has to be one
+        let token_program_id = token_program.key();
+        require!(
+            token_program_id == anchor_spl::token::ID || token_program_id == anchor_spl::token_2022::ID,
+            EscrowError::InvalidTokenProgram
+        );
+        require_keys_eq!(*mint.owner, token_program_id, EscrowError::InvalidTokenProgram);
+        let decimals = Mint::try_deserialize(&mut &mint.data.borrow()[..])?.decimals;
+
+        let create_accounts = Create {
+            payer: self.payer.clone(),
+            associated_token: to.clone(),
+            authority: self.to_owner.clone(),
+            mint: mint.clone(),
+            system_program: self.system_program.clone(),
+            token_program: token_program.clone(),
+        };
+        create_idempotent(CpiContext::new(self.programs.associated_token_program.to_account_info(), create_accounts))?;
+
+        self.check_vault(from, mint, token_program)?;
+
+        // a vault being emptied pays out everything it holds, so it can close
+        let amount = match self.close_to {
+            Some(_) => TokenAccount::try_deserialize(&mut &from.data.borrow()[..])?.amount,
+            None => asset.amount,
+        };
+
+        let transfer_accounts = TransferChecked {
+            from: from.clone(),
+            mint: mint.clone(),
+            to: to.clone(),
+            authority: self.from_owner.clone(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), transfer_accounts, self.signer_seeds);
+
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        if let Some(close_to) = self.close_to {
+            let close_accounts = CloseAccount {
+                account: from.clone(),
+                destination: close_to.clone(),
+                authority: self.from_owner.clone(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), close_accounts, self.signer_seeds);
+
+            close_account(cpi_ctx)?;
+        }
+
+        Ok(())
+    }
+
+    // Token metadata checks the metadata, edition and token record addresses, and
+    // creates `to` itself
+    fn transfer_programmable_nft(&self, accounts: &'a [AccountInfo<'info>]) -> Result<()> {
+        let [mint, from, to, metadata, edition, from_token_record, to_token_record, authorization_rules] = accounts
+        else {
+            return err!(EscrowError::InvalidRemainingAccounts);
+        };
+
+        let programs = self.programs;
+        self.check_vault(from, mint, programs.token_program.as_ref())?;
+
+        let (rules_program, rules) = match authorization_rules.key() == mpl_token_metadata::ID {
+            true => (None, None),
+            false => (Some(programs.authorization_rules_program.as_ref()), Some(authorization_rules)),
+        };
+
+        TransferV1CpiBuilder::new(programs.token_metadata_program.as_ref())
+            .token(from)
+            .token_owner(self.from_owner)
+            .destination_token(to)
+            .destination_owner(self.to_owner)
+            .mint(mint)
+            .metadata(metadata)
+            .edition(Some(edition))
+            .token_record(Some(from_token_record))
+            .destination_token_record(Some(to_token_record))
+            .authority(self.from_owner)
+            .payer(self.payer)
+            .system_program(self.system_program)
+            .sysvar_instructions(programs.sysvar_instructions.as_ref())
+            .spl_token_program(programs.token_program.as_ref())
+            .spl_ata_program(programs.associated_token_program.as_ref())
+            .authorization_rules_program(rules_program)
+            .authorization_rules(rules)
+            .amount(1)
+            .invoke_signed(self.signer_seeds)?;
+
+        if let Some(close_to) = self.close_to {
+            self.close_programmable_vault(from, close_to)?;
+        }
+
+        Ok(())
+    }
+
+    // A vault being emptied has to be the basket's own associated token account,
+    // not a decoy passed in its place
+    fn check_vault(&self, from: &AccountInfo, mint: &AccountInfo, token_program: &AccountInfo) -> Result<()> {
+        if self.close_to.is_none() {
+            return Ok(());
+        }
+
+        let vault = get_associated_token_address_with_program_id(self.from_owner.key, mint.key, token_program.key);
+        require_keys_eq!(from.key(), vault, EscrowError::InvalidVault);
+        Ok(())
+    }
+
+    // Token metadata thaws the vault to move the pNFT out. When it closes the
+    // emptied vault itself the rent lands with the basket, from_owner, and is
+    // passed on; otherwise the vault closes like any empty token account.
+    fn close_programmable_vault(&self, vault: &'a AccountInfo<'info>, close_to: &'a AccountInfo<'info>) -> Result<()> {
+        if vault.data_is_empty() {
+            if *self.from_owner.owner == crate::ID {
+                let rent_exempt = Rent::get()?.minimum_balance(self.from_owner.data_len());
+                let surplus = self.from_owner.lamports().saturating_sub(rent_exempt);
+                **self.from_owner.try_borrow_mut_lamports()? -= surplus;
+                **close_to.try_borrow_mut_lamports()? += surplus;
+            }
+            return Ok(());
+        }
+
+        let close_accounts = CloseAccount {
+            account: vault.clone(),
+            destination: close_to.clone(),
+            authority: self.from_owner.clone(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.programs.token_program.to_account_info(),
+            close_accounts,
+            self.signer_seeds,
+        );
+
+        close_account(cpi_ctx)
+    }
+}
+
+// Splits remaining_accounts into each asset's accounts, in the order the assets
+// are listed, checking each run starts with its asset's mint. Returns the runs
+// and whatever accounts are left after the last one.
+pub fn split_accounts<'a, 'info>(
+    assets: &[Asset],
+    mut accounts: &'a [AccountInfo<'info>],
+) -> Result<(Vec<&'a [AccountInfo<'info>]>, &'a [AccountInfo<'info>])> {
+    let mut asset_accounts = Vec::with_capacity(assets.len());
+
+    for asset in assets {
+        let count = match asset.kind {
+            AssetKind::Token => TOKEN_ACCOUNTS,
+            AssetKind::ProgrammableNft => PROGRAMMABLE_NFT_ACCOUNTS,
+        };
+        require!(accounts.len() >= count, EscrowError::InvalidRemainingAccounts);
+
+        let (run, rest) = accounts.split_at(count);
+        require_keys_eq!(run[0].key(), asset.mint, EscrowError::InvalidMint);
+
+        asset_accounts.push(run);
+        accounts = rest;
+    }
+
+    Ok((asset_accounts, accounts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(mint: Pubkey) -> Asset {
+        Asset { mint, amount: 10, kind: AssetKind::Token }
+    }
+
+    fn pnft(mint: Pubkey) -> Asset {
+        Asset { mint, amount: 1, kind: AssetKind::ProgrammableNft }
+    }
+
+    // Each asset's run of accounts as remaining_accounts would carry it, fresh
+    // keys standing in for everything but the mint
+    fn keys_for(assets: &[Asset]) -> Vec<Pubkey> {
+        assets
+            .iter()
+            .flat_map(|asset| {
+                let count = match asset.kind {
+                    AssetKind::Token => TOKEN_ACCOUNTS,
+                    AssetKind::ProgrammableNft => PROGRAMMABLE_NFT_ACCOUNTS,
+                };
+                core::iter::once(asset.mint).chain((1..count).map(|_| Pubkey::new_unique()))
+            })
+            .collect()
+    }
+
+    fn error_code(error: Error) -> u32 {
+        match error {
+            Error::AnchorError(error) => error.error_code_number,
+            Error::ProgramError(error) => panic!("unexpected program error {error}"),
+        }
+    }
+
+    // Runs `split_accounts` over `keys` as empty accounts, returning the length
+    // of each run and how many accounts were left over
+    fn split(assets: &[Asset], keys: &[Pubkey]) -> core::result::Result<(Vec<usize>, usize), u32> {
+        let mut lamports = vec![0u64; keys.len()];
+        let mut data = vec![Vec::<u8>::new(); keys.len()];
+
+        let infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| AccountInfo::new(key, false, true, lamports, data, &crate::ID, false, 0))
+            .collect();
+
+        split_accounts(assets, &infos)
+            .map(|(runs, rest)| (runs.iter().map(|run| run.len()).collect(), rest.len()))
+            .map_err(error_code)
+    }
+
+    #[test]
+    fn accounts_split_per_asset_in_order() {
+        let assets = [token(Pubkey::new_unique()), pnft(Pubkey::new_unique()), token(Pubkey::new_unique())];
+        let keys = keys_for(&assets);
+
+        assert_eq!(split(&assets, &keys), Ok((vec![4, 8, 4], 0)));
+
+        // the accounts of the other side of a take follow and are handed back
+        let mut longer = keys.clone();
+        longer.extend(keys_for(&[token(Pubkey::new_unique())]));
+        assert_eq!(split(&assets, &longer), Ok((vec![4, 8, 4], 4)));
+    }
+
+    #[test]
+    fn too_few_accounts_are_rejected() {
+        let assets = [token(Pubkey::new_unique()), pnft(Pubkey::new_unique())];
+        let keys = keys_for(&assets);
+
+        for missing in 1..=PROGRAMMABLE_NFT_ACCOUNTS {
+            assert_eq!(
+                split(&assets, &keys[..keys.len() - missing]),
+                Err(EscrowError::InvalidRemainingAccounts.into())
+            );
+        }
+        assert_eq!(split(&assets, &[]), Err(EscrowError::InvalidRemainingAccounts.into()));
+    }
+
+    #[test]
+    fn accounts_out_of_order_are_rejected() {
+        let first = token(Pubkey::new_unique());
+        let second = token(Pubkey::new_unique());
+
+        let keys = keys_for(&[second, first]);
+        assert_eq!(split(&[first, second], &keys), Err(EscrowError::InvalidMint.into()));
+
+        // runs of different lengths swapped over put the wrong key first too
+        let nft = pnft(Pubkey::new_unique());
+        let keys = keys_for(&[first, nft]);
+        assert_eq!(split(&[nft, first], &keys), Err(EscrowError::InvalidMint.into()));
+    }
+
+    #[test]
+    fn duplicate_mints_are_rejected() {
+        let first = token(Pubkey::new_unique());
+        let second = token(Pubkey::new_unique());
+
+        // the first asset's accounts passed again in place of the second's
+        let keys = keys_for(&[first, first]);
+        assert_eq!(split(&[first, second], &keys), Err(EscrowError::InvalidMint.into()));
+
+        // and a basket can't list a mint twice to begin with
+        let code = |assets: &[Asset]| check_assets(assets).map_err(error_code);
+        assert_eq!(code(&[first, second]), Ok(()));
+        assert_eq!(code(&[first, second, first]), Err(EscrowError::InvalidBasket.into()));
+        assert_eq!(code(&[pnft(first.mint), first]), Err(EscrowError::InvalidBasket.into()));
+    }
+}
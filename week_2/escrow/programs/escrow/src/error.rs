@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum EscrowError {
+    #[msg("Amount must be greater than zero.")]
+    ZeroAmount,
+    #[msg("Maker does not match the escrow.")]
+    InvalidMaker,
+    #[msg("Mint does not match the escrow.")]
+    InvalidMint,
+    #[msg("Fill is larger than what is left of the offer.")]
+    FillExceedsOffer,
+    #[msg("Overflow detected.")]
+    Overflow,
+    #[msg("Expiry must be in the future.")]
+    InvalidExpiry,
+    #[msg("This offer has expired.")]
+    OfferExpired,
+    #[msg("This offer has not expired.")]
+    OfferNotExpired,
+    #[msg("This offer is reserved for another taker.")]
+    InvalidTaker,
+    #[msg("Basket sides hold 1 to 5 assets with distinct mints, pNFTs one at a time.")]
+    InvalidBasket,
+    #[msg("Remaining accounts do not match the basket's assets.")]
+    InvalidRemainingAccounts,
+    #[msg("Token program does not own the mint.")]
+    InvalidTokenProgram,
+    #[msg("Vault is not the basket's associated token account for the mint.")]
+    InvalidVault,
+}
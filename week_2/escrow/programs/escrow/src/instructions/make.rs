@@ -5,11 +5,11 @@ use anchor_spl::{
     token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}
 };
 
+use crate::error::EscrowError;
 use crate::state::Escrow;
 
 #[derive(Accounts)]
 #[instruction(seed: u64)]
-
 pub struct Make<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
@@ -59,7 +59,18 @@ pub struct Make<'info> {
 }
 
 impl<'info> Make<'info> {
-    pub fn init_escrow(&mut self, seed: u64, receive: u64, bump: &MakeBumps) -> Result<()> {
+    pub fn init_escrow(
+        &mut self,
+        seed: u64,
+        receive: u64,
+        expires_at: Option<i64>,
+        taker: Option<Pubkey>,
+        bump: &MakeBumps,
+    ) -> Result<()> {
+        require!(receive != 0, EscrowError::ZeroAmount);
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > Clock::get()?.unix_timestamp, EscrowError::InvalidExpiry);
+        }
 
         self.escrow.set_inner(Escrow{
             seed,
@@ -67,6 +78,9 @@ impl<'info> Make<'info> {
             mint_a: self.mint_a.key(),
             mint_b: self.mint_b.key(),
             receive,
+            deposit: 0,
+            expires_at,
+            taker,
             bump: bump.escrow
         });
 
@@ -74,6 +88,7 @@ impl<'info> Make<'info> {
     }
 
     pub fn deposit(&mut self, deposit: u64) -> Result<()> {
+        require!(deposit != 0, EscrowError::ZeroAmount);
 
         let transfer_accounts = TransferChecked {
             from: self.maker_ata_a.to_account_info(),
@@ -92,6 +107,10 @@ impl<'info> Make<'info> {
             deposit,
             self.mint_a.decimals)?;
 
+        // what the vault actually received, net of any transfer fee
+        self.vault.reload()?;
+        self.escrow.deposit = self.vault.amount;
+
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+
+use crate::assets::*;
+use crate::error::EscrowError;
+use crate::state::{Asset, Basket};
+
+// Offers a basket of assets for another. remaining_accounts carries the offered
+// assets' accounts as laid out in `assets`, from the maker to the basket's vaults.
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct MakeBasket<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        seeds = [b"basket", maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        space = 8 + Basket::INIT_SPACE,
+        bump,
+    )]
+    pub basket: Account<'info, Basket>,
+
+    pub programs: AssetPrograms<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MakeBasket<'info> {
+    pub fn init_basket(
+        &mut self,
+        seed: u64,
+        offer: Vec<Asset>,
+        request: Vec<Asset>,
+        expires_at: Option<i64>,
+        taker: Option<Pubkey>,
+        bump: &MakeBasketBumps,
+    ) -> Result<()> {
+        check_assets(&offer)?;
+        check_assets(&request)?;
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > Clock::get()?.unix_timestamp, EscrowError::InvalidExpiry);
+        }
+
+        self.basket.set_inner(Basket {
+            seed,
+            maker: self.maker.key(),
+            offer,
+            request,
+            expires_at,
+            taker,
+            bump: bump.basket,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit(&self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        let transfer = AssetTransfer::new(
+            self.maker.as_ref(),
+            self.basket.as_ref(),
+            self.maker.as_ref(),
+            &[],
+            None,
+            &self.programs,
+            self.system_program.as_ref(),
+        );
+
+        let unused = transfer.transfer_all(&self.basket.offer, remaining_accounts)?;
+        require!(unused.is_empty(), EscrowError::InvalidRemainingAccounts);
+
+        Ok(())
+    }
+}
@@ -1,5 +1,15 @@
 pub mod make;
 pub mod take;
+pub mod refund;
+pub mod refund_expired;
+pub mod make_basket;
+pub mod take_basket;
+pub mod refund_basket;
 
 pub use make::*;
 pub use take::*;
+pub use refund::*;
+pub use refund_expired::*;
+pub use make_basket::*;
+pub use take_basket::*;
+pub use refund_basket::*;
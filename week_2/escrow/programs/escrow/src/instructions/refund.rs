@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::error::EscrowError;
+use crate::state::Escrow;
+
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_a @ EscrowError::InvalidMint,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Refund<'info> {
+    // Hands the deposit back to the maker when nobody took the offer. The escrow
+    // account itself is closed to the maker by the `close` constraint.
+    pub fn refund_and_close_vault(&mut self) -> Result<()> {
+        refund_vault(
+            &self.escrow,
+            &self.vault,
+            &self.mint_a,
+            &self.maker_ata_a,
+            self.maker.to_account_info(),
+            &self.token_program,
+        )
+    }
+}
+
+// Pays what is left in the escrow's vault out to maker_ata_a and closes the
+// vault, its rent going to the maker. Shared by `refund` and `refund_expired`.
+pub fn refund_vault<'info>(
+    escrow: &Account<'info, Escrow>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint_a: &InterfaceAccount<'info, Mint>,
+    maker_ata_a: &InterfaceAccount<'info, TokenAccount>,
+    maker: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"escrow",
+        escrow.maker.as_ref(),
+        &escrow.seed.to_le_bytes()[..],
+        &[escrow.bump],
+    ]];
+
+    let accounts = TransferChecked {
+        from: vault.to_account_info(),
+        mint: mint_a.to_account_info(),
+        to: maker_ata_a.to_account_info(),
+        authority: escrow.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+
+    transfer_checked(cpi_ctx, vault.amount, mint_a.decimals)?;
+
+    let close_accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: maker,
+        authority: escrow.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        close_accounts,
+        &signer_seeds,
+    );
+
+    close_account(cpi_ctx)?;
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::assets::*;
+use crate::error::EscrowError;
+use crate::state::Basket;
+
+// Hands the basket back to the maker. The maker can do it at any time, anyone
+// else once the offer has expired. remaining_accounts carries the offered
+// assets' accounts, basket to maker.
+#[derive(Accounts)]
+pub struct RefundBasket<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ EscrowError::InvalidMaker,
+        seeds = [b"basket", maker.key().as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump,
+    )]
+    pub basket: Account<'info, Basket>,
+
+    pub programs: AssetPrograms<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RefundBasket<'info> {
+    pub fn refund(&self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        if self.payer.key() != self.maker.key() {
+            let now = Clock::get()?.unix_timestamp;
+            let expired = self.basket.expires_at.is_some_and(|expires_at| now >= expires_at);
+            require!(expired, EscrowError::OfferNotExpired);
+        }
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"basket",
+            self.maker.to_account_info().key.as_ref(),
+            &self.basket.seed.to_le_bytes()[..],
+            &[self.basket.bump],
+        ]];
+
+        let transfer = AssetTransfer::new(
+            self.basket.as_ref(),
+            self.maker.as_ref(),
+            self.payer.as_ref(),
+            &signer_seeds,
+            Some(self.maker.as_ref()),
+            &self.programs,
+            self.system_program.as_ref(),
+        );
+
+        let unused = transfer.transfer_all(&self.basket.offer, remaining_accounts)?;
+        require!(unused.is_empty(), EscrowError::InvalidRemainingAccounts);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::error::EscrowError;
+use crate::instructions::refund::refund_vault;
+use crate::state::Escrow;
+
+// Permissionless crank: once an offer is past its expiry anyone can send the
+// deposit back to the maker and close the escrow. Rent still goes to the maker.
+#[derive(Accounts)]
+pub struct RefundExpired<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_a @ EscrowError::InvalidMint,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RefundExpired<'info> {
+    pub fn refund_and_close_vault(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let expired = self.escrow.expires_at.is_some_and(|expires_at| now >= expires_at);
+        require!(expired, EscrowError::OfferNotExpired);
+
+        refund_vault(
+            &self.escrow,
+            &self.vault,
+            &self.mint_a,
+            &self.maker_ata_a,
+            self.maker.to_account_info(),
+            &self.token_program,
+        )
+    }
+}
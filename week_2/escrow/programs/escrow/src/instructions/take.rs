@@ -5,10 +5,10 @@ use anchor_spl::{
     token_interface::{close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 
+use crate::error::EscrowError;
 use crate::state::Escrow;
 
 #[derive(Accounts)]
-
 pub struct Take<'info> {
     #[account(mut)]
     pub taker: Signer<'info>,
@@ -27,7 +27,8 @@ pub struct Take<'info> {
     pub mint_b: InterfaceAccount<'info, Mint>,
 
     #[account(
-        
+        init_if_needed,
+        payer = taker,
         associated_token::mint = mint_b,
         associated_token::authority = maker,
         associated_token::token_program = token_program
@@ -51,12 +52,17 @@ pub struct Take<'info> {
     pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_a @ EscrowError::InvalidMint,
+        has_one = mint_b @ EscrowError::InvalidMint,
         seeds = [b"escrow", escrow.maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
-        bump,
+        bump = escrow.bump,
     )]
     pub escrow: Account<'info, Escrow>,
 
     #[account(
+        mut,
         associated_token::mint = mint_a,
         associated_token::authority = escrow,
         associated_token::token_program = token_program
@@ -69,7 +75,43 @@ pub struct Take<'info> {
 }
 
 impl<'info> Take<'info> {
-    pub fn deposit(&mut self) -> Result<()> {
+    pub fn take(&mut self, amount: u64) -> Result<()> {
+        let amount_a = self.fill(amount)?;
+        self.deposit(amount)?;
+        self.withdraw(amount_a)?;
+        self.close_if_filled()?;
+        Ok(())
+    }
+
+    // mint_a due for `amount` of mint_b at the offer's price, rounded down in the
+    // maker's favour. The last fill sweeps whatever is left in the vault.
+    pub fn fill(&mut self, amount: u64) -> Result<u64> {
+        if let Some(expires_at) = self.escrow.expires_at {
+            require!(Clock::get()?.unix_timestamp < expires_at, EscrowError::OfferExpired);
+        }
+        if let Some(taker) = self.escrow.taker {
+            require_keys_eq!(self.taker.key(), taker, EscrowError::InvalidTaker);
+        }
+
+        require!(amount != 0, EscrowError::ZeroAmount);
+        require!(amount <= self.escrow.receive, EscrowError::FillExceedsOffer);
+
+        let amount_a = match amount == self.escrow.receive {
+            true => self.vault.amount,
+            false => {
+                let amount_a = self.escrow.deposit as u128 * amount as u128 / self.escrow.receive as u128;
+                u64::try_from(amount_a).map_err(|_| EscrowError::Overflow)?
+            },
+        };
+        require!(amount_a != 0, EscrowError::ZeroAmount);
+
+        self.escrow.receive -= amount;
+        self.escrow.deposit = self.escrow.deposit.saturating_sub(amount_a);
+
+        Ok(amount_a)
+    }
+
+    pub fn deposit(&mut self, amount: u64) -> Result<()> {
         let transfer_accounts = TransferChecked {
             from: self.taker_ata_b.to_account_info(),
             mint: self.mint_b.to_account_info(),
@@ -79,12 +121,12 @@ impl<'info> Take<'info> {
 
         let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
 
-        transfer_checked(cpi_ctx, self.escrow.receive, self.mint_b.decimals)?;
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)?;
 
         Ok(())
     }
 
-    pub fn widthdraw_and_close_vault(&mut self) -> Result<()> {
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
         let signer_seeds: [&[&[u8]]; 1] = [&[
             b"escrow",
             self.maker.to_account_info().key.as_ref(),
@@ -105,11 +147,28 @@ impl<'info> Take<'info> {
             &signer_seeds,
         );
 
-        transfer_checked(cpi_ctx, self.vault.amount, self.mint_a.decimals)?;
+        transfer_checked(cpi_ctx, amount, self.mint_a.decimals)?;
+
+        Ok(())
+    }
+
+    // Once nothing is left to receive, the vault and the escrow close with their
+    // rent going back to the maker who paid it
+    pub fn close_if_filled(&mut self) -> Result<()> {
+        if self.escrow.receive != 0 {
+            return Ok(());
+        }
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
 
         let close_accounts = CloseAccount {
             account: self.vault.to_account_info(),
-            destination: self.taker.to_account_info(),
+            destination: self.maker.to_account_info(),
             authority: self.escrow.to_account_info(),
         };
 
@@ -120,7 +179,8 @@ impl<'info> Take<'info> {
         );
 
         close_account(cpi_ctx)?;
-        // Close the vault account
+
+        self.escrow.close(self.maker.to_account_info())?;
 
         Ok(())
     }
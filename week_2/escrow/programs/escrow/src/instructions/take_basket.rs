@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+
+use crate::assets::*;
+use crate::error::EscrowError;
+use crate::state::Basket;
+
+// Swaps the requested assets for the basket in one go. remaining_accounts carries
+// the requested assets' accounts, taker to maker, then the offered assets',
+// basket to taker. The token vaults and the basket close to the maker.
+#[derive(Accounts)]
+pub struct TakeBasket<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ EscrowError::InvalidMaker,
+        seeds = [b"basket", maker.key().as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump,
+    )]
+    pub basket: Account<'info, Basket>,
+
+    pub programs: AssetPrograms<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TakeBasket<'info> {
+    pub fn swap(&self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        if let Some(expires_at) = self.basket.expires_at {
+            require!(Clock::get()?.unix_timestamp < expires_at, EscrowError::OfferExpired);
+        }
+        if let Some(taker) = self.basket.taker {
+            require_keys_eq!(self.taker.key(), taker, EscrowError::InvalidTaker);
+        }
+
+        let deposit = AssetTransfer::new(
+            self.taker.as_ref(),
+            self.maker.as_ref(),
+            self.taker.as_ref(),
+            &[],
+            None,
+            &self.programs,
+            self.system_program.as_ref(),
+        );
+
+        let remaining_accounts = deposit.transfer_all(&self.basket.request, remaining_accounts)?;
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"basket",
+            self.maker.to_account_info().key.as_ref(),
+            &self.basket.seed.to_le_bytes()[..],
+            &[self.basket.bump],
+        ]];
+
+        let withdraw = AssetTransfer::new(
+            self.basket.as_ref(),
+            self.taker.as_ref(),
+            self.taker.as_ref(),
+            &signer_seeds,
+            Some(self.maker.as_ref()),
+            &self.programs,
+            self.system_program.as_ref(),
+        );
+
+        let unused = withdraw.transfer_all(&self.basket.offer, remaining_accounts)?;
+        require!(unused.is_empty(), EscrowError::InvalidRemainingAccounts);
+
+        Ok(())
+    }
+}
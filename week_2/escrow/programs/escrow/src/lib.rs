@@ -2,9 +2,12 @@
 
 use anchor_lang::prelude::*;
 
+mod assets;
+mod error;
 mod instructions;
 use instructions::*;
 mod state;
+use state::Asset;
 
 declare_id!("CfSGUJwWDLjQ8UDr3KZrjf5ef6MSSwTvPQjBqP3gLZaZ");
 
@@ -12,15 +15,57 @@ declare_id!("CfSGUJwWDLjQ8UDr3KZrjf5ef6MSSwTvPQjBqP3gLZaZ");
 pub mod escrow {
     use super::*;
 
-    pub fn make(ctx: Context<Make>, seed: u64, receive: u64, deposit: u64) -> Result<()> {
-        ctx.accounts.init_escrow(seed, receive, &ctx.bumps)?;
+    pub fn make(ctx: Context<Make>, seed: u64, receive: u64, deposit: u64, expires_at: Option<i64>, taker: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.init_escrow(seed, receive, expires_at, taker, &ctx.bumps)?;
         ctx.accounts.deposit(deposit)?;
         Ok(())
     }
 
+    // Takes everything left of the offer
     pub fn take(ctx: Context<Take>) -> Result<()> {
-        ctx.accounts.deposit()?;
-        ctx.accounts.widthdraw_and_close_vault()?;
+        let amount = ctx.accounts.escrow.receive;
+        ctx.accounts.take(amount)?;
+        Ok(())
+    }
+
+    // amount: mint_b the taker pays, up to what is left of the offer
+    pub fn take_partial(ctx: Context<Take>, amount: u64) -> Result<()> {
+        ctx.accounts.take(amount)?;
+        Ok(())
+    }
+
+    pub fn refund(ctx: Context<Refund>) -> Result<()> {
+        ctx.accounts.refund_and_close_vault()?;
+        Ok(())
+    }
+
+    pub fn refund_expired(ctx: Context<RefundExpired>) -> Result<()> {
+        ctx.accounts.refund_and_close_vault()?;
+        Ok(())
+    }
+
+    // Basket offers move several assets at once, their accounts come in through
+    // remaining_accounts (see `assets`)
+    pub fn make_basket<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MakeBasket<'info>>,
+        seed: u64,
+        offer: Vec<Asset>,
+        request: Vec<Asset>,
+        expires_at: Option<i64>,
+        taker: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.init_basket(seed, offer, request, expires_at, taker, &ctx.bumps)?;
+        ctx.accounts.deposit(ctx.remaining_accounts)?;
+        Ok(())
+    }
+
+    pub fn take_basket<'info>(ctx: Context<'_, '_, 'info, 'info, TakeBasket<'info>>) -> Result<()> {
+        ctx.accounts.swap(ctx.remaining_accounts)?;
+        Ok(())
+    }
+
+    pub fn refund_basket<'info>(ctx: Context<'_, '_, 'info, 'info, RefundBasket<'info>>) -> Result<()> {
+        ctx.accounts.refund(ctx.remaining_accounts)?;
         Ok(())
     }
 }
\ No newline at end of file
@@ -7,7 +7,48 @@ pub seed: u64,
 pub maker: Pubkey,
 pub mint_a: Pubkey,
 pub mint_b: Pubkey,
+// What is left of the offer: mint_b still wanted and mint_a still held for it.
+// Partial fills take both down in proportion.
 pub receive: u64,
+pub deposit: u64,
+// Optional deadline, after which the offer can only be refunded, and optional
+// taker the offer is reserved for
+pub expires_at: Option<i64>,
+pub taker: Option<Pubkey>,
 pub bump: u8
 }
 
+
+// Most assets a basket offer holds on either side
+pub const MAX_BASKET_ASSETS: usize = 5;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum AssetKind {
+    // Any SPL or Token-2022 token, plain Metaplex NFTs included
+    Token,
+    // Metaplex programmable NFT, only movable through token metadata
+    ProgrammableNft,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct Asset {
+pub mint: Pubkey,
+pub amount: u64,
+pub kind: AssetKind
+}
+
+// An all-or-nothing swap of one set of assets for another. The offered assets
+// wait in vaults owned by the basket, one per mint.
+#[account]
+#[derive(InitSpace)]
+pub struct Basket {
+pub seed: u64,
+pub maker: Pubkey,
+#[max_len(MAX_BASKET_ASSETS)]
+pub offer: Vec<Asset>,
+#[max_len(MAX_BASKET_ASSETS)]
+pub request: Vec<Asset>,
+pub expires_at: Option<i64>,
+pub taker: Option<Pubkey>,
+pub bump: u8
+}
@@ -1,16 +1,353 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
+import { Program, BN, web3 } from "@coral-xyz/anchor";
+import { assert } from "chai";
 import { Escrow } from "../target/types/escrow";
 
+const TOKEN_PROGRAM_ID = new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const ASSOCIATED_TOKEN_PROGRAM_ID = new web3.PublicKey("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+const MINT_SIZE = 82;
+
+// Sends tx and checks it fails with the program error `code`
+const expectError = async (tx: Promise<unknown>, code: string) => {
+  try {
+    await tx;
+  } catch (err) {
+    const logs: string[] = (err as { logs?: string[] }).logs ?? [];
+    assert.include(`${err}\n${logs.join("\n")}`, code);
+    return;
+  }
+  assert.fail(`expected ${code}`);
+};
+
 describe("escrow", () => {
-  // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
 
   const program = anchor.workspace.escrow as Program<Escrow>;
+  const connection = provider.connection;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const send = (instructions: web3.TransactionInstruction[], signers: web3.Keypair[]) =>
+    web3.sendAndConfirmTransaction(connection, new web3.Transaction().add(...instructions), [payer, ...signers]);
+
+  const ata = (owner: web3.PublicKey, mint: web3.PublicKey): web3.PublicKey =>
+    web3.PublicKey.findProgramAddressSync(
+      [owner.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    )[0];
+
+  // A bare mint with the provider wallet as mint authority
+  const createMint = async (decimals = 6): Promise<web3.PublicKey> => {
+    const mint = web3.Keypair.generate();
+    const lamports = await connection.getMinimumBalanceForRentExemption(MINT_SIZE);
+
+    // InitializeMint2: tag, decimals, mint authority, no freeze authority
+    const data = Buffer.alloc(35);
+    data.writeUInt8(20, 0);
+    data.writeUInt8(decimals, 1);
+    payer.publicKey.toBuffer().copy(data, 2);
+
+    await send(
+      [
+        web3.SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space: MINT_SIZE,
+          lamports,
+          programId: TOKEN_PROGRAM_ID,
+        }),
+        new web3.TransactionInstruction({
+          programId: TOKEN_PROGRAM_ID,
+          keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+          data,
+        }),
+      ],
+      [mint]
+    );
+    return mint.publicKey;
+  };
+
+  // Creates owner's associated account for mint and mints `amount` into it
+  const fund = async (owner: web3.PublicKey, mint: web3.PublicKey, amount: number): Promise<web3.PublicKey> => {
+    const account = ata(owner, mint);
+
+    // MintTo: tag, amount
+    const data = Buffer.alloc(9);
+    data.writeUInt8(7, 0);
+    new BN(amount).toArrayLike(Buffer, "le", 8).copy(data, 1);
+
+    await send(
+      [
+        // CreateIdempotent
+        new web3.TransactionInstruction({
+          programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+          keys: [
+            { pubkey: payer.publicKey, isSigner: true, isWritable: true },
+            { pubkey: account, isSigner: false, isWritable: true },
+            { pubkey: owner, isSigner: false, isWritable: false },
+            { pubkey: mint, isSigner: false, isWritable: false },
+            { pubkey: web3.SystemProgram.programId, isSigner: false, isWritable: false },
+            { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          ],
+          data: Buffer.from([1]),
+        }),
+        new web3.TransactionInstruction({
+          programId: TOKEN_PROGRAM_ID,
+          keys: [
+            { pubkey: mint, isSigner: false, isWritable: true },
+            { pubkey: account, isSigner: false, isWritable: true },
+            { pubkey: payer.publicKey, isSigner: true, isWritable: false },
+          ],
+          data,
+        }),
+      ],
+      []
+    );
+    return account;
+  };
+
+  const balance = async (account: web3.PublicKey): Promise<number> => {
+    const info = await connection.getAccountInfo(account);
+    if (info === null) {
+      return 0;
+    }
+    return Number((await connection.getTokenAccountBalance(account)).value.amount);
+  };
+
+  const newWallet = async (): Promise<web3.Keypair> => {
+    const wallet = web3.Keypair.generate();
+    await send(
+      [
+        web3.SystemProgram.transfer({
+          fromPubkey: payer.publicKey,
+          toPubkey: wallet.publicKey,
+          lamports: web3.LAMPORTS_PER_SOL,
+        }),
+      ],
+      []
+    );
+    return wallet;
+  };
+
+  type Offer = {
+    maker: web3.Keypair;
+    mintA: web3.PublicKey;
+    mintB: web3.PublicKey;
+    escrow: web3.PublicKey;
+    vault: web3.PublicKey;
+  };
+
+  // A maker offering `deposit` of a fresh mint_a for `receive` of a fresh mint_b
+  const makeOffer = async (
+    deposit: number,
+    receive: number,
+    options: { expiresAt?: number; taker?: web3.PublicKey } = {}
+  ): Promise<Offer> => {
+    const maker = await newWallet();
+    const [mintA, mintB] = [await createMint(), await createMint()];
+    await fund(maker.publicKey, mintA, deposit);
+
+    const seed = new BN(Math.floor(Math.random() * Number.MAX_SAFE_INTEGER));
+    const [escrow] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const vault = ata(escrow, mintA);
+
+    await program.methods
+      .make(
+        seed,
+        new BN(receive),
+        new BN(deposit),
+        options.expiresAt === undefined ? null : new BN(options.expiresAt),
+        options.taker ?? null
+      )
+      .accountsPartial({
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        makerAtaA: ata(maker.publicKey, mintA),
+        escrow,
+        vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    return { maker, mintA, mintB, escrow, vault };
+  };
+
+  // A taker holding `amount` of mint_b and an empty mint_a account
+  const newTaker = async (offer: Offer, amount: number): Promise<web3.Keypair> => {
+    const taker = await newWallet();
+    await fund(taker.publicKey, offer.mintA, 0);
+    await fund(taker.publicKey, offer.mintB, amount);
+    return taker;
+  };
+
+  const takeAccounts = (offer: Offer, taker: web3.Keypair) => ({
+    taker: taker.publicKey,
+    maker: offer.maker.publicKey,
+    mintA: offer.mintA,
+    mintB: offer.mintB,
+    makerAtaB: ata(offer.maker.publicKey, offer.mintB),
+    takerAtaA: ata(taker.publicKey, offer.mintA),
+    takerAtaB: ata(taker.publicKey, offer.mintB),
+    escrow: offer.escrow,
+    vault: offer.vault,
+    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    systemProgram: web3.SystemProgram.programId,
+  });
+
+  const takePartial = (offer: Offer, taker: web3.Keypair, amount: number) =>
+    program.methods
+      .takePartial(new BN(amount))
+      .accountsPartial(takeAccounts(offer, taker))
+      .signers([taker])
+      .rpc();
+
+  const take = (offer: Offer, taker: web3.Keypair) =>
+    program.methods.take().accountsPartial(takeAccounts(offer, taker)).signers([taker]).rpc();
+
+  const refundExpired = (offer: Offer, cranker: web3.Keypair) =>
+    program.methods
+      .refundExpired()
+      .accountsPartial({
+        payer: cranker.publicKey,
+        maker: offer.maker.publicKey,
+        mintA: offer.mintA,
+        makerAtaA: ata(offer.maker.publicKey, offer.mintA),
+        escrow: offer.escrow,
+        vault: offer.vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([cranker])
+      .rpc();
+
+  // The validator's clock, which is what expiries are checked against
+  const chainTime = async (): Promise<number> => connection.getBlockTime(await connection.getSlot());
+
+  const waitUntil = async (timestamp: number) => {
+    while ((await chainTime()) < timestamp) {
+      await new Promise((resolve) => setTimeout(resolve, 500));
+    }
+  };
+
+  it("takes a whole offer and closes the escrow", async () => {
+    const offer = await makeOffer(1_000, 500);
+    const taker = await newTaker(offer, 500);
+
+    await take(offer, taker);
+
+    assert.equal(await balance(ata(taker.publicKey, offer.mintA)), 1_000);
+    assert.equal(await balance(ata(offer.maker.publicKey, offer.mintB)), 500);
+    assert.isNull(await connection.getAccountInfo(offer.escrow));
+    assert.isNull(await connection.getAccountInfo(offer.vault));
+  });
+
+  it("fills an offer in parts, rounding each part down and sweeping the vault on the last", async () => {
+    // 10 of mint_a for 3 of mint_b does not divide evenly
+    const offer = await makeOffer(10, 3);
+    const taker = await newTaker(offer, 3);
+    const takerA = ata(taker.publicKey, offer.mintA);
+
+    // 10 × 1 / 3 rounds down to 3, leaving 7 for 2
+    await takePartial(offer, taker, 1);
+    assert.equal(await balance(takerA), 3);
+    let escrow = await program.account.escrow.fetch(offer.escrow);
+    assert.equal(escrow.receive.toNumber(), 2);
+    assert.equal(escrow.deposit.toNumber(), 7);
+    assert.equal(await balance(offer.vault), 7);
+
+    // 7 × 1 / 2 rounds down to 3, leaving 4 for 1
+    await takePartial(offer, taker, 1);
+    assert.equal(await balance(takerA), 6);
+    escrow = await program.account.escrow.fetch(offer.escrow);
+    assert.equal(escrow.receive.toNumber(), 1);
+    assert.equal(escrow.deposit.toNumber(), 4);
+
+    // the last fill takes all 4 left, the rounding dust included
+    await takePartial(offer, taker, 1);
+    assert.equal(await balance(takerA), 10);
+    assert.equal(await balance(ata(offer.maker.publicKey, offer.mintB)), 3);
+    assert.isNull(await connection.getAccountInfo(offer.escrow));
+    assert.isNull(await connection.getAccountInfo(offer.vault));
+  });
+
+  it("rejects a fill larger than what is left of the offer", async () => {
+    const offer = await makeOffer(1_000, 100);
+    const taker = await newTaker(offer, 200);
+
+    await expectError(takePartial(offer, taker, 101), "FillExceedsOffer");
+
+    await takePartial(offer, taker, 60);
+    await expectError(takePartial(offer, taker, 41), "FillExceedsOffer");
+
+    // the rejected fills moved nothing
+    const escrow = await program.account.escrow.fetch(offer.escrow);
+    assert.equal(escrow.receive.toNumber(), 40);
+    assert.equal(await balance(offer.vault), 400);
+    assert.equal(await balance(ata(taker.publicKey, offer.mintB)), 140);
+  });
+
+  it("rejects an empty fill", async () => {
+    const offer = await makeOffer(1_000, 100);
+    const taker = await newTaker(offer, 100);
+
+    await expectError(takePartial(offer, taker, 0), "ZeroAmount");
+  });
+
+  it("rejects an expiry that has already passed", async () => {
+    await expectError(makeOffer(1_000, 100, { expiresAt: (await chainTime()) - 1 }), "InvalidExpiry");
+  });
+
+  it("stops takes at the expiry and lets anyone refund the maker after it", async () => {
+    const offer = await makeOffer(1_000, 100, { expiresAt: (await chainTime()) + 10 });
+    const taker = await newTaker(offer, 100);
+    const cranker = await newWallet();
+
+    // nothing to refund while the offer is live
+    await expectError(refundExpired(offer, cranker), "OfferNotExpired");
+    await takePartial(offer, taker, 40);
+
+    const escrow = await program.account.escrow.fetch(offer.escrow);
+    await waitUntil(escrow.expiresAt.toNumber());
+
+    await expectError(takePartial(offer, taker, 10), "OfferExpired");
+    await expectError(take(offer, taker), "OfferExpired");
+
+    await refundExpired(offer, cranker);
+    assert.equal(await balance(ata(offer.maker.publicKey, offer.mintA)), 600);
+    assert.equal(await balance(ata(taker.publicKey, offer.mintA)), 400);
+    assert.isNull(await connection.getAccountInfo(offer.escrow));
+    assert.isNull(await connection.getAccountInfo(offer.vault));
+  });
+
+  it("never lets an offer without an expiry be refunded by a crank", async () => {
+    const offer = await makeOffer(1_000, 100);
+    const cranker = await newWallet();
+
+    await expectError(refundExpired(offer, cranker), "OfferNotExpired");
+  });
+
+  it("reserves an offer for its designated taker", async () => {
+    const designated = await newWallet();
+    const offer = await makeOffer(1_000, 100, { taker: designated.publicKey });
+
+    const outsider = await newTaker(offer, 100);
+    await expectError(takePartial(offer, outsider, 50), "InvalidTaker");
+    await expectError(take(offer, outsider), "InvalidTaker");
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+    await fund(designated.publicKey, offer.mintA, 0);
+    await fund(designated.publicKey, offer.mintB, 100);
+    await take(offer, designated);
+    assert.equal(await balance(ata(designated.publicKey, offer.mintA)), 1_000);
+    assert.isNull(await connection.getAccountInfo(offer.escrow));
   });
 });